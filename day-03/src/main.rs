@@ -1,34 +1,71 @@
+use std::{fs::File, io::BufReader};
 
-use memory::{MemoryAlways, MemoryDoDont, MultiplyResults};
+use memory::{MemoryAlways, MemoryDoDont, MemoryScanner, MultiplyResults, ScanMode};
 use utils::{part_selection, Part, Timer};
 
 mod memory;
 
+const INPUT_PATH: &str = "./day-03/input.txt";
+
 fn main() {
     let _timer = Timer::new();
 
-    let text = std::fs::read_to_string("./day-03/input.txt").unwrap();
-
     let part = part_selection();
 
-    match part {
-        Part::One => part_1(&text),
-        Part::Two => part_2(&text),
+    let scan = scan_selection();
+
+    match (part, scan) {
+        (Part::One, Scan::InMemory) => part_1(&std::fs::read_to_string(INPUT_PATH).unwrap()),
+        (Part::Two, Scan::InMemory) => part_2(&std::fs::read_to_string(INPUT_PATH).unwrap()),
+        (Part::One, Scan::Streaming) => streaming(ScanMode::Always),
+        (Part::Two, Scan::Streaming) => streaming(ScanMode::DoDont),
     }
 }
 
 fn part_1(memory: &str) {
-    let memory = MemoryAlways::from(memory);
+    let multiply_result = MemoryAlways::try_from(memory).and_then(|m| m.multiplication_results());
 
-    let multiply_result = memory.multiplication_results();
-
-    println!("{multiply_result}");
+    match multiply_result {
+        Ok(multiply_result) => println!("{multiply_result}"),
+        Err(error) => panic!("Failed to read memory: {error}"),
+    }
 }
 
 fn part_2(memory: &str) {
-    let memory = MemoryDoDont::from(memory);
+    let multiply_result = MemoryDoDont::try_from(memory).and_then(|m| m.multiplication_results());
+
+    match multiply_result {
+        Ok(multiply_result) => println!("{multiply_result}"),
+        Err(error) => panic!("Failed to read memory: {error}"),
+    }
+}
+
+fn streaming(mode: ScanMode) {
+    let path = std::env::args().nth(3).unwrap_or(INPUT_PATH.to_string());
 
-    let multiply_result = memory.multiplication_results();
+    let reader = BufReader::new(File::open(&path).unwrap());
 
-    println!("{multiply_result}");
-}
\ No newline at end of file
+    match MemoryScanner::scan(mode, reader) {
+        Ok(multiply_result) => println!("{multiply_result}"),
+        Err(error) => panic!("Failed to scan '{path}': {error}"),
+    }
+}
+
+enum Scan {
+    InMemory,
+    Streaming,
+}
+
+fn scan_selection() -> Scan {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    if args.len() > 2 {
+        match args[2].as_str() {
+            "--in-memory" => return Scan::InMemory,
+            "--streaming" => return Scan::Streaming,
+            _ => {}
+        }
+    }
+
+    Scan::InMemory
+}
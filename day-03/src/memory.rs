@@ -1,16 +1,19 @@
+use std::io::Read;
+
 use lazy_regex::regex;
 
 #[derive(Debug, PartialEq, Eq)]
-pub struct Multiply(i32, i32);
+pub struct Multiply(i64, i64);
 
 impl Multiply {
-    pub fn multiply(&self) -> i32 {
-        self.0 * self.1
+    // the product of two i64 values always fits in an i128, only a running total can overflow
+    pub fn multiply(&self) -> i128 {
+        self.0 as i128 * self.1 as i128
     }
 }
 
 pub trait MultiplyResults {
-    fn multiplication_results(&self) -> i32;
+    fn multiplication_results(&self) -> Result<i128, String>;
 }
 
 pub struct Memory {
@@ -18,41 +21,51 @@ pub struct Memory {
 }
 
 impl MultiplyResults for Memory {
-    fn multiplication_results(&self) -> i32 {
+    fn multiplication_results(&self) -> Result<i128, String> {
         self.instructions
             .iter()
-            .map(|i| i.multiply())
-            .sum()
+            .try_fold(0, add_product)
+            .ok_or_else(|| "Multiplication results overflowed i128".to_string())
     }
 }
 
+fn add_product(total: i128, multiply: &Multiply) -> Option<i128> {
+    total.checked_add(multiply.multiply())
+}
+
+fn parse_operand(digits: &str) -> Result<i64, String> {
+    digits.parse().map_err(|_| format!("Multiply operand {digits} overflowed i64"))
+}
+
 pub struct MemoryAlways {
     memory: Memory,
 }
 
 impl MultiplyResults for MemoryAlways {
-    fn multiplication_results(&self) -> i32 {
+    fn multiplication_results(&self) -> Result<i128, String> {
         self.memory.multiplication_results()
     }
 }
 
-impl From<&str> for MemoryAlways {
-    fn from(text: &str) -> Self {
+impl TryFrom<&str> for MemoryAlways {
+    type Error = String;
+
+    fn try_from(text: &str) -> Result<Self, String> {
         let multiply_regex = regex!(r"mul\((?<a>\d+),(?<b>\d+)\)");
 
         let mut instructions = vec![];
 
         for capture in multiply_regex.captures_iter(text) {
 
-            let a = capture.name("a").unwrap().as_str().parse::<i32>().unwrap();
-            let b = capture.name("b").unwrap().as_str().parse::<i32>().unwrap();
+            let a = parse_operand(capture.name("a").unwrap().as_str())?;
+            let b = parse_operand(capture.name("b").unwrap().as_str())?;
 
             instructions.push(Multiply(a, b));
         }
 
         let memory = Memory { instructions };
 
-        Self { memory }
+        Ok(Self { memory })
     }
 }
 
@@ -61,13 +74,15 @@ pub struct MemoryDoDont {
 }
 
 impl MultiplyResults for MemoryDoDont {
-    fn multiplication_results(&self) -> i32 {
+    fn multiplication_results(&self) -> Result<i128, String> {
         self.memory.multiplication_results()
     }
 }
 
-impl From<&str> for MemoryDoDont {
-    fn from(text: &str) -> Self {
+impl TryFrom<&str> for MemoryDoDont {
+    type Error = String;
+
+    fn try_from(text: &str) -> Result<Self, String> {
         let multiply_regex = regex!(r"(?<mul>mul\((?<a>\d+),(?<b>\d+)\))|(?<do>do\(\))|(?<dont>don't\(\))");
 
         let mut instructions = vec![];
@@ -81,8 +96,8 @@ impl From<&str> for MemoryDoDont {
             } else if capture.name("dont").is_some() {
                 process_mul = false;
             } else if process_mul && capture.name("mul").is_some() {
-                let a = capture.name("a").unwrap().as_str().parse::<i32>().unwrap();
-                let b = capture.name("b").unwrap().as_str().parse::<i32>().unwrap();
+                let a = parse_operand(capture.name("a").unwrap().as_str())?;
+                let b = parse_operand(capture.name("b").unwrap().as_str())?;
     
                 instructions.push(Multiply(a, b));
             }
@@ -90,7 +105,161 @@ impl From<&str> for MemoryDoDont {
 
         let memory = Memory { instructions };
 
-        Self { memory }
+        Ok(Self { memory })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanMode {
+    Always,
+    DoDont,
+}
+
+const MULTIPLY_KEYWORD: &[u8] = b"mul(";
+const DO_KEYWORD: &[u8] = b"do()";
+const DONT_KEYWORD: &[u8] = b"don't()";
+const LONGEST_KEYWORD: usize = 7;
+
+const SCAN_BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+enum ScanState {
+    Keyword([u8; LONGEST_KEYWORD], usize),
+    FirstOperand(Option<i64>),
+    SecondOperand(i64, Option<i64>),
+}
+
+// Scans memory a chunk at a time so instructions can be split across reads - all parse state lives
+// in the scanner rather than in the chunk, so the input never needs to be held in memory at once.
+pub struct MemoryScanner {
+    mode: ScanMode,
+    state: ScanState,
+    enabled: bool,
+    offset: u64,
+    total: i128,
+}
+
+impl MemoryScanner {
+    pub fn new(mode: ScanMode) -> Self {
+        Self {
+            mode,
+            state: ScanState::Keyword([0; LONGEST_KEYWORD], 0),
+            enabled: true,
+            offset: 0,
+            total: 0,
+        }
+    }
+
+    pub fn scan<R: Read>(mode: ScanMode, mut reader: R) -> Result<i128, String> {
+        let mut scanner = MemoryScanner::new(mode);
+
+        let mut buffer = vec![0; SCAN_BUFFER_SIZE];
+
+        loop {
+            let read = reader
+                .read(&mut buffer)
+                .map_err(|e| format!("Failed to read memory at byte {}: {e}", scanner.offset))?;
+
+            if read == 0 {
+                break;
+            }
+
+            scanner.feed(&buffer[..read])?;
+        }
+
+        Ok(scanner.total())
+    }
+
+    pub fn total(&self) -> i128 {
+        self.total
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), String> {
+        for &byte in chunk {
+            // none of the keywords contain the start of another keyword past their first byte, so when a
+            // partial match fails only the current byte needs to be looked at again
+            if !self.step(byte)? {
+                self.state = ScanState::Keyword([0; LONGEST_KEYWORD], 0);
+                self.step(byte)?;
+            }
+            self.offset += 1;
+        }
+
+        Ok(())
+    }
+
+    fn step(&mut self, byte: u8) -> Result<bool, String> {
+        match self.state {
+            ScanState::Keyword(mut matched, length) => {
+                matched[length] = byte;
+                let matched_length = length + 1;
+                let candidate = &matched[..matched_length];
+
+                if candidate == MULTIPLY_KEYWORD {
+                    self.state = ScanState::FirstOperand(None);
+                } else if self.mode == ScanMode::DoDont && candidate == DO_KEYWORD {
+                    self.enabled = true;
+                    self.state = ScanState::Keyword([0; LONGEST_KEYWORD], 0);
+                } else if self.mode == ScanMode::DoDont && candidate == DONT_KEYWORD {
+                    self.enabled = false;
+                    self.state = ScanState::Keyword([0; LONGEST_KEYWORD], 0);
+                } else if self.keywords().any(|k| k.starts_with(candidate)) {
+                    self.state = ScanState::Keyword(matched, matched_length);
+                } else {
+                    // a byte that cannot start a keyword is simply skipped
+                    return Ok(length == 0);
+                }
+            },
+            ScanState::FirstOperand(operand) => {
+                if byte.is_ascii_digit() {
+                    self.state = ScanState::FirstOperand(Some(self.push_digit(operand, byte)?));
+                } else if byte == b',' && operand.is_some() {
+                    self.state = ScanState::SecondOperand(operand.unwrap(), None);
+                } else {
+                    return Ok(false);
+                }
+            },
+            ScanState::SecondOperand(a, operand) => {
+                if byte.is_ascii_digit() {
+                    self.state = ScanState::SecondOperand(a, Some(self.push_digit(operand, byte)?));
+                } else if let (b')', Some(b)) = (byte, operand) {
+                    self.multiply(a, b)?;
+                    self.state = ScanState::Keyword([0; LONGEST_KEYWORD], 0);
+                } else {
+                    return Ok(false);
+                }
+            },
+        }
+
+        Ok(true)
+    }
+
+    fn keywords(&self) -> impl Iterator<Item = &'static [u8]> {
+        let keywords: &[&'static [u8]] = match self.mode {
+            ScanMode::Always => &[MULTIPLY_KEYWORD],
+            ScanMode::DoDont => &[MULTIPLY_KEYWORD, DO_KEYWORD, DONT_KEYWORD],
+        };
+
+        keywords.iter().copied()
+    }
+
+    fn push_digit(&self, operand: Option<i64>, byte: u8) -> Result<i64, String> {
+        operand
+            .unwrap_or(0)
+            .checked_mul(10)
+            .and_then(|o| o.checked_add((byte - b'0') as i64))
+            .ok_or_else(|| format!("Multiply operand overflowed i64 at byte {}", self.offset))
+    }
+
+    fn multiply(&mut self, a: i64, b: i64) -> Result<(), String> {
+        if self.mode == ScanMode::DoDont && !self.enabled {
+            return Ok(());
+        }
+
+        self.total = add_product(self.total, &Multiply(a, b))
+            .ok_or_else(|| format!("Multiplication results overflowed i128 at byte {}", self.offset))?;
+
+        Ok(())
     }
 }
 
@@ -110,7 +279,7 @@ mod tests {
 
         let result = memory.multiplication_results();
 
-        assert_eq!(result, 161);
+        assert_eq!(result, Ok(161));
     }
 
     #[test]
@@ -119,7 +288,7 @@ mod tests {
 xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))
         ".trim();
 
-        let memory = MemoryAlways::from(text).memory;
+        let memory = MemoryAlways::try_from(text).unwrap().memory;
 
         assert_eq!(memory.instructions.len(), 4);
         assert_eq!(memory.instructions[0], Multiply(2, 4));
@@ -134,10 +303,77 @@ xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))
 xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))
         ".trim();
 
-        let memory = MemoryDoDont::from(text).memory;
+        let memory = MemoryDoDont::try_from(text).unwrap().memory;
 
         assert_eq!(memory.instructions.len(), 2);
         assert_eq!(memory.instructions[0], Multiply(2, 4));
         assert_eq!(memory.instructions[1], Multiply(8, 5));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn scanner_matches_parse() {
+        let always_text = r"
+xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))
+        ".trim();
+        let dodont_text = r"
+xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))
+        ".trim();
+
+        let always = MemoryScanner::scan(ScanMode::Always, always_text.as_bytes()).unwrap();
+        let dodont = MemoryScanner::scan(ScanMode::DoDont, dodont_text.as_bytes()).unwrap();
+
+        assert_eq!(Ok(always), MemoryAlways::try_from(always_text).unwrap().multiplication_results());
+        assert_eq!(always, 161);
+        assert_eq!(Ok(dodont), MemoryDoDont::try_from(dodont_text).unwrap().multiplication_results());
+        assert_eq!(dodont, 48);
+    }
+
+    #[test]
+    fn scanner_chunk_boundaries() {
+        let text = r"
+mmul(2,4)mul(mul(3,3)don'tdon't()mul(5,5)do(do()mul(11,8)mul(8,5)
+        ".trim().as_bytes();
+
+        let mut whole = MemoryScanner::new(ScanMode::DoDont);
+        whole.feed(text).unwrap();
+
+        assert_eq!(whole.total(), 8 + 9 + 88 + 40);
+
+        for chunk_size in 1..text.len() {
+            let mut scanner = MemoryScanner::new(ScanMode::DoDont);
+            for chunk in text.chunks(chunk_size) {
+                scanner.feed(chunk).unwrap();
+            }
+
+            assert_eq!(scanner.total(), whole.total(), "chunk size {chunk_size}");
+        }
+    }
+
+    #[test]
+    fn scanner_overflow() {
+        let large = format!("mul({},{})", i32::MAX, i32::MAX);
+
+        let total = MemoryScanner::scan(ScanMode::Always, large.as_bytes()).unwrap();
+
+        assert_eq!(total, i32::MAX as i128 * i32::MAX as i128);
+        assert_eq!(Ok(total), MemoryAlways::try_from(large.as_str()).unwrap().multiplication_results());
+
+        let operand_overflow = format!("mul({}0,2)", i64::MAX);
+
+        assert!(MemoryScanner::scan(ScanMode::Always, operand_overflow.as_bytes()).is_err());
+        assert!(MemoryAlways::try_from(operand_overflow.as_str()).is_err());
+
+        let mut scanner = MemoryScanner::new(ScanMode::Always);
+        let largest = format!("mul({},{})", i64::MAX, i64::MAX);
+        let mut result = Ok(());
+        for _ in 0..4 {
+            result = result.and_then(|_| scanner.feed(largest.as_bytes()));
+        }
+
+        assert!(result.is_err());
+
+        let largest = MemoryAlways::try_from(largest.repeat(4).as_str()).unwrap();
+
+        assert!(largest.multiplication_results().is_err());
+    }
+}
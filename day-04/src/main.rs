@@ -1,6 +1,8 @@
+use stencil::Stencil;
 use utils::{part_selection, Part, Timer};
-use word_search::WordSearch;
+use word_search::{matches_json, Highlight, WordSearch};
 
+mod stencil;
 mod word_search;
 
 fn main() {
@@ -10,24 +12,70 @@ fn main() {
 
     let part = part_selection();
 
+    let search = search_selection();
+
     match part {
-        Part::One => part_1(&text),
-        Part::Two => part_2(&text),
+        Part::One => part_1(&text, search),
+        Part::Two => part_2(&text, search),
     }
 }
 
-fn part_1(input: &str) {
+fn part_1(input: &str, search: Search) {
     let word_search = WordSearch::new("XMAS", input);
 
-    let count = word_search.search_all();
+    let matches = match search {
+        Search::Count => {
+            println!("{}", word_search.search_all());
+            return;
+        },
+        _ => word_search.search_words(&["XMAS"]),
+    };
 
-    println!("{count}");
+    match search {
+        Search::Render(highlight) => println!("{}", word_search.render(&matches, highlight)),
+        Search::Json => println!("{}", matches_json(&matches)),
+        _ => println!("{}", matches.len()),
+    }
 }
 
-fn part_2(input: &str) {
+fn part_2(input: &str, search: Search) {
     let word_search = WordSearch::new("MAS", input);
 
-    let count = word_search.search_x_all();
+    let matches = match search {
+        Search::Count => {
+            println!("{}", word_search.search_x_all());
+            return;
+        },
+        _ => word_search.search_stencil(&Stencil::cross("MAS")),
+    };
+
+    match search {
+        Search::Render(highlight) => println!("{}", word_search.render(&matches, highlight)),
+        Search::Json => println!("{}", matches_json(&matches)),
+        _ => println!("{}", matches.len()),
+    }
+}
+
+enum Search {
+    Count,
+    Matches,
+    Render(Highlight),
+    Json,
+}
+
+fn search_selection() -> Search {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    if args.len() > 2 {
+        match args[2].as_str() {
+            "--count" => return Search::Count,
+            "--matches" => return Search::Matches,
+            "--render" => return Search::Render(Highlight::Dots),
+            "--highlight" => return Search::Render(Highlight::Colour),
+            "--json" => return Search::Json,
+            _ => {}
+        }
+    }
 
-    println!("{count}");
+    Search::Count
 }
@@ -0,0 +1,112 @@
+use utils::Direction;
+
+pub const STENCIL_WILDCARD: char = '.';
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StencilVariant {
+    pub rotation: Direction,
+    pub reflected: bool,
+    pub cells: Vec<(i32, i32, char)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Stencil {
+    variants: Vec<StencilVariant>,
+}
+
+impl Stencil {
+    pub fn new(cells: Vec<(i32, i32, char)>) -> Self {
+        assert!(!cells.is_empty(), "Stencil requires at least one non-wildcard cell");
+
+        let mut variants: Vec<StencilVariant> = vec![];
+
+        for reflected in [false, true] {
+            for clicks in 0..4 {
+                let rotation = Direction::North.orthogonal_rotate(clicks);
+
+                let transformed = cells
+                    .iter()
+                    .map(|&(row, column, c)| {
+                        let column = if reflected { -column } else { column };
+                        let (row, column) = rotate_clockwise(row, column, clicks);
+                        (row, column, c)
+                    })
+                    .collect::<Vec<_>>();
+
+                let cells = normalize(transformed);
+
+                // symmetric stencils map onto themselves, which would count the same match more than once
+                if variants.iter().all(|v| v.cells != cells) {
+                    variants.push(StencilVariant { rotation, reflected, cells });
+                }
+            }
+        }
+
+        Self { variants }
+    }
+
+    pub fn cross(word: &str) -> Self {
+        let length = word.chars().count();
+        if length < 3 || length.is_multiple_of(2) {
+            panic!("Cross stencil requires a word with an odd number of characters, at least 3 '{word}'");
+        }
+
+        let mut cells = vec![];
+
+        for (index, c) in word.chars().enumerate() {
+            let index = index as i32;
+            let opposite = length as i32 - 1 - index;
+
+            cells.push((index, index, c));
+            if index != opposite {
+                cells.push((opposite, index, c));
+            }
+        }
+
+        Self::new(cells)
+    }
+
+    pub fn variants(&self) -> &[StencilVariant] {
+        &self.variants
+    }
+}
+
+impl From<&str> for Stencil {
+    fn from(text: &str) -> Self {
+        let mut cells = vec![];
+
+        for (row, line) in text.trim().lines().enumerate() {
+            for (column, c) in line.trim().chars().enumerate() {
+                if c != STENCIL_WILDCARD {
+                    cells.push((row as i32, column as i32, c));
+                }
+            }
+        }
+
+        Self::new(cells)
+    }
+}
+
+fn rotate_clockwise(row: i32, column: i32, clicks: u8) -> (i32, i32) {
+    let mut rotated = (row, column);
+
+    for _ in 0..clicks {
+        rotated = (rotated.1, -rotated.0);
+    }
+
+    rotated
+}
+
+fn normalize(mut cells: Vec<(i32, i32, char)>) -> Vec<(i32, i32, char)> {
+    let min_row = cells.iter().map(|c| c.0).min().unwrap();
+    let min_column = cells.iter().map(|c| c.1).min().unwrap();
+
+    for cell in cells.iter_mut() {
+        cell.0 -= min_row;
+        cell.1 -= min_column;
+    }
+
+    cells.sort();
+
+    cells
+}
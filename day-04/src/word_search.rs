@@ -1,8 +1,117 @@
+use std::collections::HashSet;
+
+use aho_corasick::AhoCorasick;
 use utils::{Direction, Grid, Position};
 
+use crate::stencil::Stencil;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordMatch {
+    pub word: String,
+    pub start: Position,
+    pub direction: Direction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StencilMatch {
+    pub origin: Position,
+    pub rotation: Direction,
+    pub reflected: bool,
+    pub cells: Vec<Position>,
+}
+
+pub trait GridMatch {
+    fn cells(&self) -> Vec<Position>;
+
+    fn to_json(&self) -> String;
+}
+
+impl GridMatch for WordMatch {
+    fn cells(&self) -> Vec<Position> {
+        let mut cells = vec![self.start.clone()];
+
+        for _ in 1..self.word.chars().count() {
+            let next = cells.last().unwrap().adjacent(self.direction);
+            cells.push(next);
+        }
+
+        cells
+    }
+
+    fn to_json(&self) -> String {
+        format!(
+            r#"{{"word":{},"start":{},"direction":"{}","cells":{}}}"#,
+            json_string(&self.word),
+            json_position(&self.start),
+            self.direction,
+            json_positions(&self.cells()),
+        )
+    }
+}
+
+impl GridMatch for StencilMatch {
+    fn cells(&self) -> Vec<Position> {
+        self.cells.clone()
+    }
+
+    fn to_json(&self) -> String {
+        format!(
+            r#"{{"origin":{},"rotation":"{}","reflected":{},"cells":{}}}"#,
+            json_position(&self.origin),
+            self.rotation,
+            self.reflected,
+            json_positions(&self.cells),
+        )
+    }
+}
+
+pub fn matches_json<M: GridMatch>(matches: &[M]) -> String {
+    let matches = matches.iter().map(|m| m.to_json()).collect::<Vec<_>>();
+
+    format!("[{}]", matches.join(","))
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::from('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str(r#"\""#),
+            '\\' => json.push_str(r"\\"),
+            c if c.is_control() => json.push_str(&format!(r"\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+
+    json
+}
+
+fn json_position(position: &Position) -> String {
+    format!("[{},{}]", position.0, position.1)
+}
+
+fn json_positions(positions: &[Position]) -> String {
+    let positions = positions.iter().map(json_position).collect::<Vec<_>>();
+
+    format!("[{}]", positions.join(","))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    Dots,
+    Colour,
+}
+
+const UNUSED_LETTER: char = '.';
+const COLOUR_START: &str = "\x1b[1;32m";
+const COLOUR_END: &str = "\x1b[0m";
+
 pub struct WordSearch {
     search_text: String,
     flattened_input: String,
+    letters: Vec<char>,
     grid: Grid,
 }
 
@@ -13,7 +122,145 @@ impl WordSearch {
 
         let search_text = search_text.to_string();
 
-        Self { search_text, flattened_input, grid }
+        let letters = flattened_input.chars().collect();
+
+        Self { search_text, flattened_input, letters, grid }
+    }
+
+    pub fn search_words(&self, words: &[&str]) -> Vec<WordMatch> {
+        let mut words = words.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        words.sort();
+        words.dedup();
+        assert!(words.iter().all(|w| !w.is_empty()), "Search words cannot be empty");
+
+        // each word is searched for forwards and backwards so every line only has to be scanned in one direction
+        let patterns = words
+            .iter()
+            .cloned()
+            .chain(words.iter().map(|w| w.chars().rev().collect()))
+            .collect::<Vec<String>>();
+
+        let automaton = AhoCorasick::new(&patterns).unwrap();
+
+        let mut matches = vec![];
+
+        for direction in [Direction::East, Direction::South, Direction::SouthEast, Direction::SouthWest] {
+            for line_start in self.line_starts(direction) {
+                let mut line_positions = vec![];
+                let mut line_text = String::new();
+                let mut char_indices = vec![];
+
+                let mut position = line_start;
+                while self.grid.validate_position(&position, false) {
+                    let c = self.letters[self.grid.get_index(&position).unwrap()];
+                    char_indices.extend(std::iter::repeat_n(line_positions.len(), c.len_utf8()));
+                    line_text.push(c);
+                    line_positions.push(position.clone());
+                    position = position.adjacent(direction);
+                }
+
+                for found in automaton.find_overlapping_iter(&line_text) {
+                    let pattern = found.pattern().as_usize();
+                    let first = char_indices[found.start()];
+                    let last = char_indices[found.end() - 1];
+
+                    let (word, start, direction) = if pattern < words.len() {
+                        (&words[pattern], &line_positions[first], direction)
+                    } else {
+                        (&words[pattern - words.len()], &line_positions[last], direction.flip())
+                    };
+
+                    matches.push(WordMatch { word: word.clone(), start: start.clone(), direction });
+                }
+            }
+        }
+
+        matches.sort_by(|a, b| {
+            (a.start.0, a.start.1, a.direction, &a.word).cmp(&(b.start.0, b.start.1, b.direction, &b.word))
+        });
+
+        matches
+    }
+
+    fn line_starts(&self, direction: Direction) -> Vec<Position> {
+        let rows = 0..self.grid.row_count;
+        let columns = 0..self.grid.column_count;
+        let last_column = self.grid.column_count - 1;
+
+        match direction {
+            Direction::East => rows.map(|r| Position(r, 0)).collect(),
+            Direction::South => columns.map(|c| Position(0, c)).collect(),
+            Direction::SouthEast => rows
+                .map(|r| Position(r, 0))
+                .chain(columns.skip(1).map(|c| Position(0, c)))
+                .collect(),
+            Direction::SouthWest => columns
+                .map(|c| Position(0, c))
+                .chain(rows.skip(1).map(|r| Position(r, last_column)))
+                .collect(),
+            _ => panic!("Lines are only scanned East, South, SouthEast, and SouthWest - {direction}")
+        }
+    }
+
+    pub fn search_stencil(&self, stencil: &Stencil) -> Vec<StencilMatch> {
+        let mut matches = vec![];
+
+        for row in 0..self.grid.row_count {
+            for column in 0..self.grid.column_count {
+                for variant in stencil.variants() {
+                    let found = variant.cells.iter().all(|&(row_offset, column_offset, c)| {
+                        let position = Position(row + row_offset, column + column_offset);
+                        self.grid.validate_position(&position, false)
+                            && self.letters[self.grid.get_index(&position).unwrap()] == c
+                    });
+
+                    if found {
+                        let cells = variant.cells
+                            .iter()
+                            .map(|&(row_offset, column_offset, _)| Position(row + row_offset, column + column_offset))
+                            .collect();
+
+                        matches.push(StencilMatch {
+                            origin: Position(row, column),
+                            rotation: variant.rotation,
+                            reflected: variant.reflected,
+                            cells,
+                        });
+                    }
+                }
+            }
+        }
+
+        matches
+    }
+
+    pub fn used_indices<M: GridMatch>(&self, matches: &[M]) -> HashSet<usize> {
+        matches
+            .iter()
+            .flat_map(|m| m.cells())
+            .map(|p| self.grid.get_index(&p).unwrap())
+            .collect()
+    }
+
+    pub fn render<M: GridMatch>(&self, matches: &[M], highlight: Highlight) -> String {
+        let used = self.used_indices(matches);
+
+        let mut text = String::new();
+
+        for (index, letter) in self.letters.iter().enumerate() {
+            if index > 0 && self.grid.get_position(index).unwrap().1 == 0 {
+                text.push('\n');
+            }
+
+            match (used.contains(&index), highlight) {
+                (true, Highlight::Dots) => text.push(*letter),
+                (true, Highlight::Colour) => text.push_str(&format!("{COLOUR_START}{letter}{COLOUR_END}")),
+                (false, Highlight::Dots) => text.push(UNUSED_LETTER),
+                (false, Highlight::Colour) => text.push(*letter),
+            }
+        }
+
+        text
     }
 
     pub fn search_all(&self) -> usize {
@@ -271,4 +518,143 @@ MXMXAXMASX
 
         assert_eq!(count, 9);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn search_words_example() {
+        let word_search = WordSearch::new("XMAS", EXAMPLE);
+
+        let matches = word_search.search_words(&["XMAS"]);
+
+        assert_eq!(matches.len(), word_search.search_all());
+        assert_eq!(matches.len(), 18);
+        assert!(matches.contains(&WordMatch { word: "XMAS".to_string(), start: Position(4, 0), direction: Direction::East }));
+        assert!(matches.contains(&WordMatch { word: "XMAS".to_string(), start: Position(9, 9), direction: Direction::NorthWest }));
+    }
+
+    #[test]
+    fn search_words_multiple() {
+        let word_search = WordSearch::new("XMAS", EXAMPLE);
+
+        let matches = word_search.search_words(&["XMAS", "MAS", "SAMX"]);
+
+        let count = |word: &str| matches.iter().filter(|m| m.word == word).count();
+
+        assert_eq!(count("XMAS"), 18);
+        assert_eq!(count("SAMX"), 18);
+        assert_eq!(count("MAS"), WordSearch::new("MAS", EXAMPLE).search_all());
+    }
+
+    #[test]
+    fn search_stencil_example() {
+        let word_search = WordSearch::new("MAS", EXAMPLE);
+
+        let stencil = Stencil::from(r"
+M.S
+.A.
+M.S
+        ");
+
+        assert_eq!(stencil.variants().len(), 4);
+
+        let matches = word_search.search_stencil(&stencil);
+
+        assert_eq!(matches.len(), word_search.search_x_all());
+        assert_eq!(matches.len(), 9);
+        assert_eq!(word_search.search_stencil(&Stencil::cross("MAS")).len(), 9);
+        assert_eq!(matches[0].origin, Position(0, 1));
+        assert_eq!(matches[0].rotation, Direction::North);
+        assert!(!matches[0].reflected);
+    }
+
+    #[test]
+    fn search_stencil_reflections() {
+        let word_search = WordSearch::new("XMAS", r"
+AB..
+.C..
+..CB
+...A
+        ");
+
+        let stencil = Stencil::from(r"
+AB
+.C
+        ");
+
+        assert_eq!(stencil.variants().len(), 8);
+
+        let matches = word_search.search_stencil(&stencil);
+
+        let matches = matches.iter().map(|m| (m.origin.clone(), m.rotation, m.reflected)).collect::<Vec<_>>();
+
+        assert_eq!(matches, vec![
+            (Position(0, 0), Direction::North, false),
+            (Position(2, 2), Direction::East, true),
+        ]);
+    }
+
+    #[test]
+    fn render_word_matches_example() {
+        let word_search = WordSearch::new("XMAS", EXAMPLE);
+
+        let matches = word_search.search_words(&["XMAS"]);
+
+        let rendered = word_search.render(&matches, Highlight::Dots);
+
+        let expected = r"
+....XXMAS.
+.SAMXMS...
+...S..A...
+..A.A.MS.X
+XMASAMX.MM
+X.....XA.A
+S.S.S.S.SS
+.A.A.A.A.A
+..M.M.M.MM
+.X.X.XMASX
+        ".trim();
+
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn render_stencil_matches_example() {
+        let word_search = WordSearch::new("MAS", EXAMPLE);
+
+        let matches = word_search.search_stencil(&Stencil::cross("MAS"));
+
+        let rendered = word_search.render(&matches, Highlight::Dots);
+
+        let expected = r"
+.M.S......
+..A..MSMS.
+.M.S.MAA..
+..A.ASMSM.
+.M.S.M....
+..........
+S.S.S.S.S.
+.A.A.A.A..
+M.M.M.M.M.
+..........
+        ".trim();
+
+        assert_eq!(rendered, expected);
+        assert_eq!(word_search.used_indices(&matches).len(), expected.chars().filter(|c| c.is_alphabetic()).count());
+    }
+
+    #[test]
+    fn matches_json_example() {
+        let word_search = WordSearch::new("XMAS", EXAMPLE);
+
+        let matches = word_search.search_words(&["XMAS"]);
+
+        let json = matches_json(&matches[..1]);
+
+        assert_eq!(json, r#"[{"word":"XMAS","start":[0,4],"direction":"SouthEast","cells":[[0,4],[1,5],[2,6],[3,7]]}]"#);
+
+        let matches = word_search.search_stencil(&Stencil::cross("MAS"));
+
+        let json = matches_json(&matches[..1]);
+
+        assert_eq!(json, r#"[{"origin":[0,1],"rotation":"North","reflected":false,"cells":[[0,1],[0,3],[1,2],[2,1],[2,3]]}]"#);
+    }
+}
@@ -2,6 +2,7 @@ use page_order::PageOrdering;
 use utils::{part_selection, Part, Timer};
 
 mod page_order;
+mod rule_graph;
 
 fn main() {
     let _timer = Timer::new();
@@ -10,9 +11,13 @@ fn main() {
 
     let part = part_selection();
 
-    match part {
-        Part::One => part_1(&text),
-        Part::Two => part_2(&text),
+    match output_selection() {
+        Output::Total => match part {
+            Part::One => part_1(&text),
+            Part::Two => part_2(&text),
+        },
+        Output::Diagnostics => diagnostics(&text),
+        Output::Dot(printing_index) => dot(&text, printing_index),
     }
 }
 
@@ -27,7 +32,65 @@ fn part_1(input: &str) {
 fn part_2(input: &str) {
     let mut page_ordering = PageOrdering::from(input);
 
-    let (_, count) = page_ordering.fix_order_printing();
+    let (_, count) = match page_ordering.fix_order_printing() {
+        Ok(result) => result,
+        Err(cycle) => panic!("{cycle}"),
+    };
 
     println!("{count}");
 }
+
+fn diagnostics(input: &str) {
+    let mut page_ordering = PageOrdering::from(input);
+
+    for (index, violations) in page_ordering.printing_violations() {
+        println!("Printing {index} violates {} rules", violations.len());
+        for violation in violations {
+            println!(
+                "    {}|{} - {} at {}, {} at {}",
+                violation.rule.0,
+                violation.rule.1,
+                violation.rule.0,
+                violation.left_position,
+                violation.rule.1,
+                violation.right_position,
+            );
+        }
+    }
+
+    let (valid_count, valid_total) = page_ordering.check_order_printing();
+
+    println!("{valid_count} valid printings with middle page total {valid_total}");
+
+    match page_ordering.fix_order_printing() {
+        Ok((fixed_printings, fixed_total)) => println!("{fixed_printings} fixed printings with middle page total {fixed_total}"),
+        Err(cycle) => println!("{cycle}"),
+    }
+}
+
+fn dot(input: &str, printing_index: Option<usize>) {
+    let page_ordering = PageOrdering::from(input);
+
+    println!("{}", page_ordering.rule_graph_dot(printing_index));
+}
+
+enum Output {
+    Total,
+    Diagnostics,
+    Dot(Option<usize>),
+}
+
+fn output_selection() -> Output {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    if args.len() > 2 {
+        match args[2].as_str() {
+            "--total" => return Output::Total,
+            "--diagnostics" => return Output::Diagnostics,
+            "--dot" => return Output::Dot(args.get(3).map(|i| i.parse::<usize>().unwrap())),
+            _ => {}
+        }
+    }
+
+    Output::Total
+}
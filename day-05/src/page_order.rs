@@ -1,10 +1,20 @@
+use crate::rule_graph::{first_positions, RuleCycle, RuleGraph};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PageOrderRule(pub u32, pub u32);
 
 pub type Printing = Vec<u32>;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleViolation {
+    pub rule: PageOrderRule,
+    pub left_position: usize,
+    pub right_position: usize,
+}
+
 trait ValidateRules<Rule> {
     fn validate(&self, rules: &[Rule]) -> bool;
-    fn fix(&mut self, rules: &[Rule]) -> usize;
+    fn validate_detailed(&self, rules: &[Rule]) -> Vec<RuleViolation>;
 }
 
 impl ValidateRules<PageOrderRule> for Printing {
@@ -25,27 +35,23 @@ impl ValidateRules<PageOrderRule> for Printing {
         true
     }
 
-    fn fix(&mut self, rules: &[PageOrderRule]) -> usize {
-        let mut moved_count = 0;
+    fn validate_detailed(&self, rules: &[PageOrderRule]) -> Vec<RuleViolation> {
+        let mut violations = vec![];
 
-        for rule in rules {
-            let left_index = self.iter().position(|&p| p == rule.0);
-            let right_index = self.iter().position(|&p| p == rule.1);
+        let positions = first_positions(self);
 
-            if left_index.is_some() && right_index.is_some() {
-                let left_index = left_index.unwrap();
-                let right_index = right_index.unwrap();
-                if left_index >= right_index {
-                    
-                    let right = self.remove(right_index);
-                    self.insert(left_index, right);
+        for rule in rules {
+            let left_index = positions.get(&rule.0).copied();
+            let right_index = positions.get(&rule.1).copied();
 
-                    moved_count += 1;
+            if let (Some(left_position), Some(right_position)) = (left_index, right_index) {
+                if left_position >= right_position {
+                    violations.push(RuleViolation { rule: *rule, left_position, right_position });
                 }
             }
         }
 
-        moved_count
+        violations
     }
 }
 
@@ -94,23 +100,39 @@ impl PageOrdering {
         (count, total)
     }
 
-    pub fn fix_order_printing(&mut self) -> (usize, u32) {
-        let mut fixed_count = 0;
+    pub fn printing_violations(&self) -> Vec<(usize, Vec<RuleViolation>)> {
+        self.printings
+            .iter()
+            .map(|printing| printing.validate_detailed(&self.order_rules))
+            .enumerate()
+            .filter(|(_, violations)| !violations.is_empty())
+            .collect()
+    }
+
+    pub fn rule_graph_dot(&self, printing_index: Option<usize>) -> String {
+        let printing = printing_index.map(|index| {
+            self.printings
+                .get(index)
+                .unwrap_or_else(|| panic!("Printing {index} does not exist - there are {} printings", self.printings.len()))
+                .as_slice()
+        });
+
+        RuleGraph::new(&self.order_rules).to_dot(printing)
+    }
+
+    pub fn fix_order_printing(&mut self) -> Result<(usize, u32), RuleCycle> {
+        let mut fixed_printings = 0;
         let mut fixed_total = 0;
 
+        let rule_graph = RuleGraph::new(&self.order_rules);
+
         for printing in self.printings.iter_mut() {
-            let count = printing.fix(&self.order_rules);
-            fixed_count += count;
+            let ordered = rule_graph.order(printing)?;
 
-            if count > 0 {
+            if ordered != *printing {
+                *printing = ordered;
 
-                while !printing.validate(&self.order_rules) {
-                    let count = printing.fix(&self.order_rules);
-                    if count == 0 {
-                        panic!("Page order is invalid but fix does not move any pages");
-                    }
-                    fixed_count += count;
-                }
+                fixed_printings += 1;
 
                 let mid_point = printing.len() / 2;
 
@@ -118,7 +140,7 @@ impl PageOrdering {
             }
         }
 
-        (fixed_count, fixed_total)
+        Ok((fixed_printings, fixed_total))
     }
 
 }
@@ -250,10 +272,109 @@ mod tests {
     fn fix_example() {
         let mut page_ordering = PageOrdering::from(EXAMPLE);
 
-        let (fixed_count, fixed_total) = page_ordering.fix_order_printing();
+        let (fixed_printings, fixed_total) = page_ordering.fix_order_printing().unwrap();
 
-        assert_eq!(5, fixed_count);
+        // three of the printings are out of order
+        assert_eq!(3, fixed_printings);
 
         assert_eq!(123, fixed_total);
+
+        assert_eq!(page_ordering.printings[3], vec![97, 75, 47, 61, 53]);
+        assert_eq!(page_ordering.printings[4], vec![61, 29, 13]);
+        assert_eq!(page_ordering.printings[5], vec![97, 75, 47, 29, 13]);
+    }
+
+    #[test]
+    fn validate_detailed_example() {
+        let page_ordering = PageOrdering::from(EXAMPLE);
+
+        let violations = page_ordering.printing_violations();
+
+        assert_eq!(violations.iter().map(|(index, _)| *index).collect::<Vec<_>>(), vec![3, 4, 5]);
+
+        assert_eq!(violations[0].1, vec![
+            RuleViolation { rule: PageOrderRule(97, 75), left_position: 1, right_position: 0 },
+        ]);
+        assert_eq!(violations[1].1, vec![
+            RuleViolation { rule: PageOrderRule(29, 13), left_position: 2, right_position: 1 },
+        ]);
+        assert_eq!(violations[2].1.len(), 4);
+        assert!(violations[2].1.contains(&RuleViolation { rule: PageOrderRule(47, 13), left_position: 4, right_position: 1 }));
+    }
+
+    #[test]
+    fn rule_graph_dot_printing() {
+        let page_ordering = PageOrdering::from(EXAMPLE);
+
+        let dot = page_ordering.rule_graph_dot(Some(4));
+
+        assert_eq!(dot, r#"
+digraph rules {
+    13 [label="13 (1)"];
+    29 [label="29 (2)"];
+    61 [label="61 (0)"];
+    29 -> 13 [color=red];
+    61 -> 13;
+    61 -> 29;
+}
+        "#.trim());
+
+        let dot = page_ordering.rule_graph_dot(None);
+
+        assert_eq!(dot.lines().filter(|l| l.contains("->")).count(), 21);
+    }
+
+    #[test]
+    fn repeated_page_diagnostics_agree() {
+        let page_ordering = PageOrdering::from(r"
+11|22
+
+22,11,22
+        ");
+
+        // the first 22 comes before 11, so the rule is broken even though another 22 comes after it
+        let violations = page_ordering.printing_violations();
+
+        assert_eq!(violations[0].1, vec![RuleViolation { rule: PageOrderRule(11, 22), left_position: 1, right_position: 0 }]);
+        assert!(page_ordering.rule_graph_dot(Some(0)).contains("11 -> 22 [color=red];"));
+    }
+
+    #[test]
+    fn fix_cycle() {
+        let mut page_ordering = PageOrdering::from(r"
+11|22
+22|33
+33|11
+44|11
+
+44,33,22,11
+        ");
+
+        let cycle = page_ordering.fix_order_printing().unwrap_err();
+
+        assert_eq!(cycle, RuleCycle(vec![11, 22, 33]));
+        assert_eq!(cycle.to_string(), "Page order rules form a cycle 11 -> 22 -> 33 -> 11");
+    }
+
+    #[test]
+    fn order_ignores_rules_outside_printing() {
+        let rules = vec![
+            PageOrderRule(11, 22),
+            PageOrderRule(22, 33),
+            PageOrderRule(33, 11),
+        ];
+
+        let rule_graph = RuleGraph::new(&rules);
+
+        assert_eq!(rule_graph.order(&[22, 11]), Ok(vec![11, 22]));
+        assert_eq!(rule_graph.order(&[33, 22, 44]), Ok(vec![22, 33, 44]));
+    }
+
+    #[test]
+    fn order_repeated_pages() {
+        let rule_graph = RuleGraph::new(&[PageOrderRule(11, 22)]);
+
+        assert_eq!(rule_graph.order(&[22, 11, 11]), Ok(vec![11, 11, 22]));
+        assert_eq!(rule_graph.order(&[22, 33, 22, 11]), Ok(vec![33, 11, 22, 22]));
     }
 }
\ No newline at end of file
@@ -0,0 +1,181 @@
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, fmt};
+
+use crate::page_order::{PageOrderRule, Printing};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleCycle(pub Vec<u32>);
+
+impl fmt::Display for RuleCycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pages = self.0
+            .iter()
+            .chain(self.0.first())
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>();
+
+        write!(f, "Page order rules form a cycle {}", pages.join(" -> "))
+    }
+}
+
+// where each page first appears, which is the position a rule is checked against when a page repeats
+pub fn first_positions(printing: &[u32]) -> HashMap<u32, usize> {
+    let mut positions = HashMap::new();
+
+    for (index, &page) in printing.iter().enumerate() {
+        positions.entry(page).or_insert(index);
+    }
+
+    positions
+}
+
+#[derive(Debug, Default)]
+pub struct RuleGraph {
+    successors: BTreeMap<u32, BTreeSet<u32>>,
+    predecessors: BTreeMap<u32, BTreeSet<u32>>,
+}
+
+impl RuleGraph {
+    pub fn new(rules: &[PageOrderRule]) -> Self {
+        let mut graph = RuleGraph::default();
+
+        for rule in rules {
+            graph.successors.entry(rule.0).or_default().insert(rule.1);
+            graph.predecessors.entry(rule.1).or_default().insert(rule.0);
+        }
+
+        graph
+    }
+
+    /*
+     * each distinct page is one node, keyed by where it first appears, and any repeats of a page are kept
+     * together with that first copy
+     */
+    pub fn order(&self, printing: &[u32]) -> Result<Printing, RuleCycle> {
+        // rules only apply when both pages are present, so the graph is limited to the pages in the printing
+        let positions = first_positions(printing);
+
+        let mut copies: HashMap<u32, usize> = HashMap::new();
+
+        for &page in printing.iter() {
+            *copies.entry(page).or_default() += 1;
+        }
+
+        let first = |index: usize| positions[&printing[index]] == index;
+
+        let mut in_degrees = printing
+            .iter()
+            .map(|page| self.predecessors_within(*page, &positions).count())
+            .collect::<Vec<_>>();
+
+        // pages with no constraint between them keep their original relative order
+        let mut available = in_degrees
+            .iter()
+            .enumerate()
+            .filter(|&(index, &degree)| degree == 0 && first(index))
+            .map(|(index, _)| index)
+            .collect::<BTreeSet<_>>();
+
+        let mut ordered = Printing::with_capacity(printing.len());
+
+        while let Some(index) = available.pop_first() {
+            let page = printing[index];
+            ordered.extend(std::iter::repeat_n(page, copies[&page]));
+
+            for successor in self.successors_within(page, &positions) {
+                let successor_index = positions[&successor];
+                in_degrees[successor_index] -= 1;
+                if in_degrees[successor_index] == 0 {
+                    available.insert(successor_index);
+                }
+            }
+        }
+
+        if ordered.len() == printing.len() {
+            Ok(ordered)
+        } else {
+            let remaining = printing
+                .iter()
+                .enumerate()
+                .filter(|&(index, _)| first(index) && in_degrees[index] > 0)
+                .map(|(index, &page)| (page, index))
+                .collect::<HashMap<_, _>>();
+
+            Err(self.find_cycle(printing, &remaining))
+        }
+    }
+
+    pub fn to_dot(&self, printing: Option<&[u32]>) -> String {
+        let positions = printing.map(first_positions);
+
+        let included = |page: &u32| positions.as_ref().is_none_or(|p| p.contains_key(page));
+
+        let mut lines = vec!["digraph rules {".to_string()];
+
+        let pages = self.successors
+            .keys()
+            .chain(self.predecessors.keys())
+            .filter(|p| included(p))
+            .collect::<BTreeSet<_>>();
+
+        // nodes are only declared when they carry a printing position, otherwise the edges are enough
+        if let Some(positions) = positions.as_ref() {
+            for page in pages.iter() {
+                lines.push(format!(r#"    {page} [label="{page} ({})"];"#, positions[page]));
+            }
+        }
+
+        for (page, successors) in self.successors.iter().filter(|(p, _)| included(p)) {
+            for successor in successors.iter().filter(|p| included(p)) {
+                let violated = positions.as_ref().is_some_and(|p| p[page] >= p[successor]);
+
+                if violated {
+                    lines.push(format!("    {page} -> {successor} [color=red];"));
+                } else {
+                    lines.push(format!("    {page} -> {successor};"));
+                }
+            }
+        }
+
+        lines.push("}".to_string());
+
+        lines.join("\n")
+    }
+
+    fn find_cycle(&self, printing: &[u32], remaining: &HashMap<u32, usize>) -> RuleCycle {
+        // every page left over still has a predecessor left over, so walking backwards must revisit a page
+        let mut page = *printing.iter().find(|p| remaining.contains_key(p)).unwrap();
+
+        let mut path = vec![];
+        let mut visited = HashMap::new();
+
+        while !visited.contains_key(&page) {
+            visited.insert(page, path.len());
+            path.push(page);
+            page = self.predecessors_within(page, remaining).next().unwrap();
+        }
+
+        let mut cycle = path.split_off(visited[&page]);
+        cycle.reverse();
+
+        let smallest = cycle.iter().enumerate().min_by_key(|(_, &p)| p).unwrap().0;
+        cycle.rotate_left(smallest);
+
+        RuleCycle(cycle)
+    }
+
+    fn successors_within<'a>(&'a self, page: u32, pages: &'a HashMap<u32, usize>) -> impl Iterator<Item = u32> + 'a {
+        Self::within(self.successors.get(&page), pages)
+    }
+
+    fn predecessors_within<'a>(&'a self, page: u32, pages: &'a HashMap<u32, usize>) -> impl Iterator<Item = u32> + 'a {
+        Self::within(self.predecessors.get(&page), pages)
+    }
+
+    fn within<'a>(linked: Option<&'a BTreeSet<u32>>, pages: &'a HashMap<u32, usize>) -> impl Iterator<Item = u32> + 'a {
+        linked
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|p| pages.contains_key(p))
+    }
+}
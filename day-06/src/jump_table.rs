@@ -0,0 +1,172 @@
+use utils::{Direction, Grid, Position};
+
+#[derive(Debug, Clone)]
+pub struct JumpTable {
+    grid: Grid,
+    obstacles: Vec<bool>,
+    next_obstacles: Vec<[Option<usize>; 4]>,
+}
+
+impl JumpTable {
+    pub fn new(grid: &Grid, obstacle_indices: &[usize]) -> Self {
+        let cell_count = (grid.row_count * grid.column_count) as usize;
+
+        let mut obstacles = vec![false; cell_count];
+        for &obstacle_index in obstacle_indices {
+            obstacles[obstacle_index] = true;
+        }
+
+        let mut jump_table = Self {
+            grid: grid.clone(),
+            obstacles,
+            next_obstacles: vec![[None; 4]; cell_count],
+        };
+
+        // sweeping against each direction carries the last obstacle seen to every cell behind it
+        for row in 0..grid.row_count {
+            jump_table.sweep(Position(row, grid.column_count - 1), Direction::East);
+            jump_table.sweep(Position(row, 0), Direction::West);
+        }
+
+        for column in 0..grid.column_count {
+            jump_table.sweep(Position(0, column), Direction::North);
+            jump_table.sweep(Position(grid.row_count - 1, column), Direction::South);
+        }
+
+        jump_table
+    }
+
+    fn sweep(&mut self, start: Position, direction: Direction) {
+        let slot = slot(direction);
+        let backwards = direction.flip();
+
+        let mut next_obstacle = None;
+        let mut position = start;
+
+        while self.grid.validate_position(&position, false) {
+            let index = self.grid.get_index(&position).unwrap();
+
+            self.next_obstacles[index][slot] = next_obstacle;
+
+            if self.obstacles[index] {
+                next_obstacle = Some(index);
+            }
+
+            position = position.adjacent(backwards);
+        }
+    }
+
+    pub fn is_obstacle(&self, index: usize) -> bool {
+        self.obstacles[index]
+    }
+
+    pub fn next_obstacle(&self, index: usize, direction: Direction) -> Option<usize> {
+        self.next_obstacles[index][slot(direction)]
+    }
+
+    // Answers as if one extra obstacle had been added, without changing the table - so a single table can be
+    // shared between threads that each try out a different obstacle.
+    pub fn next_obstacle_with(&self, index: usize, direction: Direction, extra_obstacle: usize) -> Option<usize> {
+        let next_obstacle = self.next_obstacle(index, direction);
+
+        let position = self.grid.get_position(index).unwrap();
+        let extra_position = self.grid.get_position(extra_obstacle).unwrap();
+
+        let extra_distance = match direction {
+            Direction::North if extra_position.1 == position.1 => position.0 - extra_position.0,
+            Direction::East if extra_position.0 == position.0 => extra_position.1 - position.1,
+            Direction::South if extra_position.1 == position.1 => extra_position.0 - position.0,
+            Direction::West if extra_position.0 == position.0 => position.1 - extra_position.1,
+            _ => return next_obstacle,
+        };
+
+        if extra_distance <= 0 {
+            return next_obstacle;
+        }
+
+        match next_obstacle {
+            Some(next_index) => {
+                let next_position = self.grid.get_position(next_index).unwrap();
+                let next_distance = next_position.0.abs_diff(position.0) + next_position.1.abs_diff(position.1);
+
+                if (extra_distance as u32) < next_distance {
+                    Some(extra_obstacle)
+                } else {
+                    Some(next_index)
+                }
+            },
+            None => Some(extra_obstacle),
+        }
+    }
+
+    pub fn add_obstacle(&mut self, obstacle_index: usize) {
+        if self.is_obstacle(obstacle_index) {
+            return;
+        }
+
+        self.obstacles[obstacle_index] = true;
+
+        let obstacle_position = self.grid.get_position(obstacle_index).unwrap();
+
+        // only the cells between the new obstacle and the previous one behind it can see it first
+        for &direction in Direction::orthogonal() {
+            let slot = slot(direction);
+            let backwards = direction.flip();
+
+            let mut position = obstacle_position.adjacent(backwards);
+
+            while self.grid.validate_position(&position, false) {
+                let index = self.grid.get_index(&position).unwrap();
+
+                self.next_obstacles[index][slot] = Some(obstacle_index);
+
+                if self.obstacles[index] {
+                    break;
+                }
+
+                position = position.adjacent(backwards);
+            }
+        }
+    }
+
+    pub fn remove_obstacle(&mut self, obstacle_index: usize) {
+        if !self.is_obstacle(obstacle_index) {
+            return;
+        }
+
+        self.obstacles[obstacle_index] = false;
+
+        let obstacle_position = self.grid.get_position(obstacle_index).unwrap();
+
+        // the cells that saw the obstacle first now see whatever the obstacle itself sees beyond it
+        for &direction in Direction::orthogonal() {
+            let slot = slot(direction);
+            let backwards = direction.flip();
+            let beyond = self.next_obstacles[obstacle_index][slot];
+
+            let mut position = obstacle_position.adjacent(backwards);
+
+            while self.grid.validate_position(&position, false) {
+                let index = self.grid.get_index(&position).unwrap();
+
+                self.next_obstacles[index][slot] = beyond;
+
+                if self.obstacles[index] {
+                    break;
+                }
+
+                position = position.adjacent(backwards);
+            }
+        }
+    }
+}
+
+fn slot(direction: Direction) -> usize {
+    match direction {
+        Direction::North => 0,
+        Direction::East => 1,
+        Direction::South => 2,
+        Direction::West => 3,
+        _ => panic!("Guard can only travel ^>v<")
+    }
+}
@@ -1,18 +1,32 @@
-use map::Map;
+use std::time::Instant;
+
+use map::{generate_map, Evaluation, Map};
+use patrol::{PatrolEnd, TurnPolicy};
 use utils::{part_selection, Part, Timer};
 
+mod jump_table;
 mod map;
+mod patrol;
 
 fn main() {
     let _timer = Timer::new();
 
-    let text = std::fs::read_to_string("./day-06/input.txt").unwrap();
-
     let part = part_selection();
 
+    if let Some((policies, path)) = patrol_selection() {
+        let text = std::fs::read_to_string(path).unwrap();
+        patrol(&text, &policies);
+        return;
+    }
+
+    let text = std::fs::read_to_string("./day-06/input.txt").unwrap();
+
     match part {
         Part::One => part_1(&text),
-        Part::Two => part_2(&text),
+        Part::Two => match evaluation_selection() {
+            EvaluationSelection::Run(evaluation) => part_2(&text, evaluation),
+            EvaluationSelection::Benchmark(size) => benchmark(size),
+        },
     }
 }
 
@@ -24,10 +38,135 @@ fn part_1(input: &str) {
     println!("{}", unique_guard_indixes.len());
 }
 
-fn part_2(input: &str) {
+fn part_2(input: &str, evaluation: Evaluation) {
     let map = Map::from(input);
 
-    let locations = map.force_loop_locations();
+    let locations = map.force_loop_locations(evaluation);
+
+    if explain_selection() {
+        for obstruction in locations.iter() {
+            let obstacle = map.position(obstruction.obstacle_index);
+            let entry = map.position(obstruction.guard_loop.entry_index);
+            println!(
+                "Obstruction at ({}, {}) loops the guard from ({}, {}) heading {} over {} steps and {} cells",
+                obstacle.0,
+                obstacle.1,
+                entry.0,
+                entry.1,
+                obstruction.guard_loop.entry_direction,
+                obstruction.guard_loop.length,
+                obstruction.guard_loop.cells.len(),
+            );
+        }
+    }
 
     println!("{}", locations.len());
 }
+
+fn explain_selection() -> bool {
+    std::env::args().skip(2).any(|a| a == "--explain")
+}
+
+fn patrol(input: &str, policies: &[TurnPolicy]) {
+    let map = Map::from(input);
+
+    let report = map.simulate_patrol(policies);
+
+    for (number, guard) in report.guards.iter().enumerate() {
+        let start = map.position(guard.start_index);
+        let end = match guard.end {
+            PatrolEnd::Exit(tick) => format!("exits at tick {tick}"),
+            PatrolEnd::Loop(tick) => format!("repeats itself from tick {tick}"),
+        };
+        println!(
+            "Guard {number} starting at ({}, {}) heading {} covers {} cells and {end}",
+            start.0,
+            start.1,
+            guard.start_direction,
+            guard.coverage.len(),
+        );
+    }
+
+    for collision in report.collisions.iter() {
+        let position = map.position(collision.index);
+        println!(
+            "{:?} collision at tick {} at ({}, {}) between guards {:?}",
+            collision.kind,
+            collision.tick,
+            position.0,
+            position.1,
+            collision.guards,
+        );
+    }
+
+    println!("{} cells covered in {} ticks", report.combined_coverage().len(), report.ticks);
+}
+
+fn patrol_selection() -> Option<(Vec<TurnPolicy>, String)> {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    if args.get(2).is_none_or(|a| a != "--patrol") {
+        return None;
+    }
+
+    let policies = args
+        .get(3)
+        .map(|p| p.split(',').map(TurnPolicy::from).collect())
+        .unwrap_or(vec![TurnPolicy::from("right")]);
+
+    let path = args.get(4).cloned().unwrap_or("./day-06/input.txt".to_string());
+
+    Some((policies, path))
+}
+
+fn benchmark(size: usize) {
+    let map = Map::from(generate_map(size, 5, 2024).as_str());
+
+    let candidates = map.loop_candidates().len();
+
+    assert!(candidates > 0, "Generated map has no candidate obstructions - the guard never leaves it");
+
+    println!("Checking {candidates} candidate obstructions on a {size}x{size} map");
+
+    let start = Instant::now();
+    let sequential = map.force_loop_locations(Evaluation::Sequential);
+    let sequential_seconds = start.elapsed().as_secs_f64();
+
+    println!("Sequential found {} loop locations in {sequential_seconds} seconds", sequential.len());
+
+    let thread_count = default_thread_count();
+
+    let start = Instant::now();
+    let parallel = map.force_loop_locations(Evaluation::Parallel(thread_count));
+    let parallel_seconds = start.elapsed().as_secs_f64();
+
+    println!("Parallel with {thread_count} threads found {} loop locations in {parallel_seconds} seconds", parallel.len());
+
+    assert_eq!(sequential, parallel, "Sequential and parallel evaluation disagree");
+}
+
+enum EvaluationSelection {
+    Run(Evaluation),
+    Benchmark(usize),
+}
+
+fn default_thread_count() -> usize {
+    std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+fn evaluation_selection() -> EvaluationSelection {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    let count_arg = |default: usize| args.get(3).and_then(|n| n.parse::<usize>().ok()).unwrap_or(default);
+
+    if args.len() > 2 {
+        match args[2].as_str() {
+            "--sequential" => return EvaluationSelection::Run(Evaluation::Sequential),
+            "--parallel" => return EvaluationSelection::Run(Evaluation::Parallel(count_arg(default_thread_count()))),
+            "--benchmark" => return EvaluationSelection::Benchmark(count_arg(1000)),
+            _ => {}
+        }
+    }
+
+    EvaluationSelection::Run(Evaluation::Sequential)
+}
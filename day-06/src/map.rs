@@ -1,28 +1,55 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use utils::{Direction, Grid, Position};
+use utils::{Direction, Grid, Position, Random};
 
+use crate::{jump_table::JumpTable, patrol::{Collision, CollisionKind, GuardPatrol, PatrolEnd, PatrolReport, TurnPolicy}};
+
+#[derive(Clone)]
 pub struct Map {
-    starting_guard: Guard,
-    obstacle_indices: Vec<usize>,
+    guards: Vec<Guard>,
+    jump_table: JumpTable,
     grid: Grid,
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuardLoop {
+    pub entry_index: usize,
+    pub entry_direction: Direction,
+    pub length: usize,
+    pub cells: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum MovementEnd {
     Exit,
-    Loop,
+    Loop(GuardLoop),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Evaluation {
+    Sequential,
+    Parallel(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoopObstruction {
+    pub obstacle_index: usize,
+    pub guard_loop: GuardLoop,
 }
 
+type Waypoint = (usize, Direction);
+
 impl Map {
     pub fn predict_guard(&self) -> (Vec<usize>, HashSet<usize>, MovementEnd) {
 
-        let mut guard = self.starting_guard.clone();
+        let mut guard = self.starting_guard().clone();
         let mut guard_indices = vec![guard.index];
         let mut unique_guard_indices = HashSet::new();
         unique_guard_indices.insert(guard.index);
 
-        let mut loop_counter = 0;
+        let mut waypoints = vec![];
+        let mut seen_waypoints = HashMap::new();
+        self.add_waypoint(&guard, &mut waypoints, &mut seen_waypoints);
 
         let movement_end = loop {
             let (new_index, movement, hit_obstacle) = self.move_guard(&guard);
@@ -31,32 +58,93 @@ impl Map {
 
             guard_indices.extend(&movement);
 
-            let previous_unique_count = unique_guard_indices.len();
-
             unique_guard_indices.extend(&movement);
 
-            let current_unique_count = unique_guard_indices.len();
-
             if hit_obstacle {
-                if movement.len() > 0 && previous_unique_count == current_unique_count {
-                    loop_counter += 1;
-                } else if previous_unique_count != current_unique_count {
-                    loop_counter = 0;
-                }
+                guard.direction = guard.direction.clockwise_orthogonal();
 
-                if loop_counter == 4 {
-                    break MovementEnd::Loop;
+                if let Some(guard_loop) = self.add_waypoint(&guard, &mut waypoints, &mut seen_waypoints) {
+                    break MovementEnd::Loop(guard_loop);
                 }
-
-                guard.direction = guard.direction.clockwise_orthogonal();
             } else {
                 break MovementEnd::Exit;
             }
+        };
+
+        (guard_indices, unique_guard_indices, movement_end)
+    }
+
+    // A waypoint is where the guard starts moving in a straight line. Guard movement only depends on its cell and
+    // heading, so reaching the same waypoint twice means it will repeat everything in between forever.
+    fn add_waypoint(&self, guard: &Guard, waypoints: &mut Vec<Waypoint>, seen_waypoints: &mut HashMap<Waypoint, usize>) -> Option<GuardLoop> {
+        let waypoint = (guard.index, guard.direction);
+
+        if let Some(&repeat) = seen_waypoints.get(&waypoint) {
+            return Some(self.describe_loop(waypoints, repeat));
+        }
+
+        seen_waypoints.insert(waypoint, waypoints.len());
+        waypoints.push(waypoint);
+
+        None
+    }
+
+    fn describe_loop(&self, waypoints: &[Waypoint], repeat: usize) -> GuardLoop {
+        let cycle = &waypoints[repeat..];
+
+        let mut length = 0;
+        let mut cells = vec![];
+        let mut unique_cells = HashSet::new();
+
+        for (segment, &(start_index, direction)) in cycle.iter().enumerate() {
+            let (end_index, _) = cycle[(segment + 1) % cycle.len()];
+
+            let mut position = self.grid.get_position(start_index).unwrap();
+            let mut index = start_index;
+
+            loop {
+                if unique_cells.insert(index) {
+                    cells.push(index);
+                }
+
+                if index == end_index {
+                    break;
+                }
+
+                position = position.adjacent(direction);
+                index = self.grid.get_index(&position).unwrap();
+                length += 1;
+            }
+        }
 
+        let (repeat_index, repeat_direction) = waypoints[repeat];
 
+        // The guard can only join the loop on the line leading into the repeated waypoint, either where it
+        // started that line itself or where the loop starts it - whichever is closer to the repeated waypoint.
+        let (entry_index, entry_direction) = if repeat == 0 {
+            (repeat_index, repeat_direction)
+        } else {
+            let (path_start, path_direction) = waypoints[repeat - 1];
+            let (cycle_start, cycle_direction) = *cycle.last().unwrap();
+
+            if path_direction == cycle_direction {
+                let distance = |index| {
+                    let position = self.grid.get_position(index).unwrap();
+                    let repeat_position = self.grid.get_position(repeat_index).unwrap();
+                    position.0.abs_diff(repeat_position.0) + position.1.abs_diff(repeat_position.1)
+                };
+
+                if distance(path_start) <= distance(cycle_start) {
+                    (path_start, path_direction)
+                } else {
+                    (cycle_start, cycle_direction)
+                }
+            } else {
+                (repeat_index, repeat_direction)
+            }
         };
 
-        (guard_indices, unique_guard_indices, movement_end)
+        GuardLoop { entry_index, entry_direction, length, cells }
     }
 
     fn move_guard(&self, guard: &Guard) -> (usize, Vec<usize>, bool) {
@@ -91,133 +179,337 @@ impl Map {
     }
 
     fn find_next_index(&self, start_index: usize, direction: &Direction) -> (usize, usize, bool) {
-        let (same_column, increasing) = direction.line();
+        let starting_position = self.grid.get_position(start_index).unwrap();
 
-        let blocking_obstacles = self.obstacle_indices.iter()
-            .filter(|&&oi| {
-                if same_column  {
-                    if !self.grid.same_column(start_index, oi) {
-                        return false;
-                    }
-                } else {
-                    if !self.grid.same_row(start_index, oi) {
-                        return false;
-                    }
-                }
+        let (end_position, hit_obstacle) = match self.jump_table.next_obstacle(start_index, *direction) {
+            Some(obstacle_index) => {
+                let obstacle_position = self.grid.get_position(obstacle_index).unwrap();
+                (obstacle_position.adjacent(direction.flip()), true)
+            },
+            None => {
+                let end_position = match direction {
+                    Direction::North => Position(0, starting_position.1),
+                    Direction::East => Position(starting_position.0, self.grid.column_count - 1),
+                    Direction::South => Position(self.grid.row_count - 1, starting_position.1),
+                    Direction::West => Position(starting_position.0, 0),
+                    _ => panic!("Guard can only travel ^>v<")
+                };
+                (end_position, false)
+            },
+        };
 
-                if increasing {
-                    oi > start_index
-                } else {
-                    oi < start_index
-                }
-            }).collect::<Vec<_>>();
+        let distance = (end_position.0.abs_diff(starting_position.0) + end_position.1.abs_diff(starting_position.1)) as usize;
 
-        let starting_position = self.grid.get_position(start_index).unwrap();
+        let end_index = self.grid.get_index(&end_position).unwrap();
 
-        if blocking_obstacles.len() == 0 {
-            let (end_position, distance) = if same_column {
-                let end_position = Position(
-                    if increasing {
-                        self.grid.row_count - 1
-                    } else {
-                        0
-                    },
-                    starting_position.1
-                );
-                let distance = (end_position.0 - starting_position.0).abs() as usize;
-                (end_position, distance)
-            } else {
-                let end_position = Position(
-                    starting_position.0,
-                    if increasing {
-                        self.grid.column_count - 1
-                    } else {
-                        0
-                    }
-                );
-                let distance = (end_position.1 - starting_position.1).abs() as usize;
-                (end_position, distance)
-            };
+        (end_index, distance, hit_obstacle)
+    }
 
-            let end_index = self.grid.get_index(&end_position).unwrap();
+    // Jumps straight from turn to turn without visiting the cells in between, which are only walked to describe
+    // a loop once one is found.
+    fn predict_turns(&self) -> MovementEnd {
+        self.predict_turns_with(|index, direction| self.jump_table.next_obstacle(index, direction))
+    }
 
-            (end_index, distance, false)
-        } else {
-            let blocking_index = if increasing {
-                 **blocking_obstacles.first().unwrap()
-            } else {
-                **blocking_obstacles.last().unwrap()
-            };
+    fn predict_turns_with<F>(&self, next_obstacle: F) -> MovementEnd
+    where
+        F: Fn(usize, Direction) -> Option<usize>,
+    {
+        let mut guard = self.starting_guard().clone();
 
-            let obstacle_position = self.grid.get_position(blocking_index).unwrap();
+        let mut waypoints = vec![];
+        let mut seen_waypoints = HashMap::new();
+        self.add_waypoint(&guard, &mut waypoints, &mut seen_waypoints);
 
-            let (end_position, distance) = if same_column {
-                let end_position = Position(
-                    if increasing {
-                        obstacle_position.0 - 1
-                    } else {
-                        obstacle_position.0 + 1
-                    },
-                    starting_position.1
-                );
-                let distance = (end_position.0 - starting_position.0).abs() as usize;
-                (end_position, distance)
-            } else {
-                let end_position = Position(
-                    starting_position.0,
-                    if increasing {
-                        obstacle_position.1 - 1
-                    } else {
-                        obstacle_position.1 + 1
-                    }
-                );
-                let distance = (end_position.1 - starting_position.1).abs() as usize;
-                (end_position, distance)
+        loop {
+            let Some(obstacle_index) = next_obstacle(guard.index, guard.direction) else {
+                break MovementEnd::Exit;
             };
-            
-            let end_index = self.grid.get_index(&end_position).unwrap();
 
-            (end_index, distance, true)
+            let obstacle_position = self.grid.get_position(obstacle_index).unwrap();
+            guard.index = self.grid.get_index(&obstacle_position.adjacent(guard.direction.flip())).unwrap();
+
+            guard.direction = guard.direction.clockwise_orthogonal();
+
+            if let Some(guard_loop) = self.add_waypoint(&guard, &mut waypoints, &mut seen_waypoints) {
+                break MovementEnd::Loop(guard_loop);
+            }
         }
     }
 
-    pub fn force_loop_locations(&self) -> Vec<usize> {
-        let mut force_loop_obstacle_locations = vec![];
-
+    // Cells on the guard's patrol where a new obstruction could be placed - none if the guard already loops.
+    pub fn loop_candidates(&self) -> Vec<usize> {
         let (_, unique_guard_indicies, movement_end) = self.predict_guard();
 
-        if movement_end == MovementEnd::Exit {
-            for guard_movement_index in unique_guard_indicies.into_iter() {
-                if self.starting_guard.index == guard_movement_index {
+        if movement_end != MovementEnd::Exit {
+            return vec![];
+        }
+
+        let mut candidates = unique_guard_indicies
+            .into_iter()
+            .filter(|&i| i != self.starting_guard().index)
+            .collect::<Vec<_>>();
+        candidates.sort();
+
+        candidates
+    }
+
+    pub fn force_loop_locations(&self, evaluation: Evaluation) -> Vec<LoopObstruction> {
+        let candidates = self.loop_candidates();
+
+        match evaluation {
+            Evaluation::Sequential => self.force_loop_sequential(&candidates),
+            Evaluation::Parallel(thread_count) => self.force_loop_parallel(&candidates, thread_count),
+        }
+    }
+
+    // One copy of the map has each candidate added and then taken away again, which only updates the cells in line
+    // with it, so each candidate costs time proportional to the number of turns plus the width of the map.
+    fn force_loop_sequential(&self, candidates: &[usize]) -> Vec<LoopObstruction> {
+        let mut alternate_map = self.clone();
+
+        candidates
+            .iter()
+            .filter_map(|&obstacle_index| {
+                alternate_map.add_obstacle(obstacle_index);
+                let alternate_end = alternate_map.predict_turns();
+                alternate_map.remove_obstacle(obstacle_index);
+
+                match alternate_end {
+                    MovementEnd::Loop(guard_loop) => Some(LoopObstruction { obstacle_index, guard_loop }),
+                    MovementEnd::Exit => None,
+                }
+            })
+            .collect()
+    }
+
+    fn force_loop_parallel(&self, candidates: &[usize], thread_count: usize) -> Vec<LoopObstruction> {
+        let chunk_size = candidates.len().div_ceil(thread_count.max(1)).max(1);
+
+        // chunks are joined in order and candidates are sorted, so the output matches the sequential evaluation
+        std::thread::scope(|scope| {
+            let handles = candidates
+                .chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || chunk.iter().filter_map(|&i| self.loop_with_obstacle(i)).collect::<Vec<_>>()))
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect()
+        })
+    }
+
+    // The extra obstacle is checked alongside the jump table rather than added to it, so every thread can share the
+    // same table and each candidate costs time proportional to the number of turns.
+    fn loop_with_obstacle(&self, obstacle_index: usize) -> Option<LoopObstruction> {
+        let alternate_end = self.predict_turns_with(|index, direction| {
+            self.jump_table.next_obstacle_with(index, direction, obstacle_index)
+        });
+
+        match alternate_end {
+            MovementEnd::Loop(guard_loop) => Some(LoopObstruction { obstacle_index, guard_loop }),
+            MovementEnd::Exit => None,
+        }
+    }
+
+    // Predicting the guard and forcing loops is the puzzle, which has exactly one guard - several guards are only
+    // simulated together through simulate_patrol.
+    fn starting_guard(&self) -> &Guard {
+        assert!(
+            self.guards.len() == 1,
+            "The guard puzzle needs exactly one guard - found {}, use --patrol to simulate several",
+            self.guards.len(),
+        );
+
+        &self.guards[0]
+    }
+
+    // Every guard takes one step per tick, or turns in place when blocked. Guards don't block each other, they
+    // only collide. A guard is done once it leaves the map or reaches a state it has been in before - from then on
+    // it repeats itself, so its coverage can't grow.
+    pub fn simulate_patrol(&self, policies: &[TurnPolicy]) -> PatrolReport {
+        assert!(
+            policies.len() == 1 || policies.len() == self.guards.len(),
+            "Expected one turn policy for all guards or one for each of the {} guards - found {}",
+            self.guards.len(),
+            policies.len(),
+        );
+
+        let policy = |guard: usize| if policies.len() == 1 { &policies[0] } else { &policies[guard] };
+
+        let mut guards = self.guards.clone();
+        let mut turn_counts = vec![0; guards.len()];
+        let mut seen_states = guards
+            .iter()
+            .map(|g| HashSet::from([(g.index, g.direction, 0)]))
+            .collect::<Vec<_>>();
+        let mut ends: Vec<Option<PatrolEnd>> = vec![None; guards.len()];
+
+        let mut patrols = guards
+            .iter()
+            .map(|g| GuardPatrol {
+                start_index: g.index,
+                start_direction: g.direction,
+                coverage: HashSet::from([g.index]),
+                end: PatrolEnd::Exit(0),
+            })
+            .collect::<Vec<_>>();
+
+        let mut collisions = vec![];
+        let mut tick = 0;
+
+        while ends.iter().any(|e| e.is_none()) {
+            tick += 1;
+
+            let previous_indices = guards.iter().map(|g| g.index).collect::<Vec<_>>();
+
+            for (number, guard) in guards.iter_mut().enumerate() {
+                if matches!(ends[number], Some(PatrolEnd::Exit(_))) {
                     continue;
                 }
 
-                let alternate_map = self.alternate(guard_movement_index);
+                let next_position = self.grid.get_position(guard.index).unwrap().adjacent(guard.direction);
+
+                if !self.grid.validate_position(&next_position, false) {
+                    ends[number] = Some(PatrolEnd::Exit(tick));
+                    continue;
+                }
 
-                let (_, _, alternate_end) = alternate_map.predict_guard();
+                let next_index = self.grid.get_index(&next_position).unwrap();
 
-                if alternate_end == MovementEnd::Loop {
-                    // let position = self.grid.get_position(guard_movement_index).unwrap();
-                    // println!("Loop caused by obstruction at ({}. {})", position.0, position.1);
-                    force_loop_obstacle_locations.push(guard_movement_index);
+                if self.jump_table.is_obstacle(next_index) {
+                    let policy = policy(number);
+                    guard.direction = policy.turn(guard.direction, turn_counts[number]);
+                    turn_counts[number] = (turn_counts[number] + 1) % policy.period();
+                } else {
+                    guard.index = next_index;
+                    patrols[number].coverage.insert(next_index);
+                }
+
+                if ends[number].is_none() && !seen_states[number].insert((guard.index, guard.direction, turn_counts[number])) {
+                    ends[number] = Some(PatrolEnd::Loop(tick));
+                }
+            }
+
+            let active = |number: &usize| !matches!(ends[*number], Some(PatrolEnd::Exit(_)));
+
+            for a in (0..guards.len()).filter(active) {
+                let same_cell = (a + 1..guards.len())
+                    .filter(active)
+                    .filter(|&b| guards[b].index == guards[a].index)
+                    .collect::<Vec<_>>();
+
+                let earlier_same_cell = (0..a).filter(active).any(|b| guards[b].index == guards[a].index);
+
+                if !same_cell.is_empty() && !earlier_same_cell {
+                    let mut colliding = vec![a];
+                    colliding.extend(same_cell);
+                    collisions.push(Collision { tick, index: guards[a].index, guards: colliding, kind: CollisionKind::SameCell });
+                }
+
+                for b in (a + 1..guards.len()).filter(active) {
+                    let swapped = guards[a].index == previous_indices[b]
+                        && guards[b].index == previous_indices[a]
+                        && guards[a].index != guards[b].index;
+
+                    if swapped {
+                        collisions.push(Collision { tick, index: guards[a].index, guards: vec![a, b], kind: CollisionKind::Swap });
+                    }
                 }
             }
+
+            // once every guard is done nothing new can be covered, and collisions from here on only repeat
+            if ends.iter().all(|e| e.is_some()) {
+                break;
+            }
+        }
+
+        for (patrol, end) in patrols.iter_mut().zip(ends) {
+            patrol.end = end.unwrap();
+        }
+
+        PatrolReport { ticks: tick, guards: patrols, collisions }
+    }
+
+    pub fn position(&self, index: usize) -> Position {
+        self.grid.get_position(index).unwrap()
+    }
+
+    pub fn add_obstacle(&mut self, obstacle_index: usize) {
+        self.jump_table.add_obstacle(obstacle_index);
+    }
+
+    pub fn remove_obstacle(&mut self, obstacle_index: usize) {
+        self.jump_table.remove_obstacle(obstacle_index);
+    }
+}
+
+// Builds a square map with obstacles scattered at the given percentage, using a fixed seed so benchmark runs are
+// repeatable. The guard starts in the middle pointing North and is steered by turning obstacles along a clear
+// corridor that spirals outwards until it leaves the map, so the base patrol always exits after crossing the map many
+// times - every cell it covers is a candidate for a new obstruction.
+pub fn generate_map(size: usize, obstacle_percent: u64, seed: u64) -> String {
+    let mut random = Random::new(seed);
+
+    let (corridor, turn) = (1, 2);
+    let mut cells = vec![0; size * size];
+
+    let guard = ((size / 2) as i64, (size / 2) as i64);
+    let inside = |(row, column): (i64, i64)| row >= 0 && column >= 0 && row < size as i64 && column < size as i64;
+    let index = |(row, column): (i64, i64)| row as usize * size + column as usize;
+
+    // arms grow every second turn, leaving a band of scattered obstacles between each of about ten laps
+    let gap = (size as i64 / 20).max(4);
+    let mut position = guard;
+
+    'spiral: for (arm, direction) in [(-1, 0), (0, 1), (1, 0), (0, -1)].into_iter().cycle().enumerate() {
+        for _ in 0..gap * (arm as i64 / 2 + 1) {
+            cells[index(position)] = corridor;
+
+            position = (position.0 + direction.0, position.1 + direction.1);
+
+            if !inside(position) {
+                break 'spiral;
+            }
+        }
+
+        cells[index(position)] = corridor;
+
+        let obstacle = (position.0 + direction.0, position.1 + direction.1);
+
+        if !inside(obstacle) {
+            // the guard walks straight off the map from here
+            let mut position = obstacle;
+            while inside(position) {
+                cells[index(position)] = corridor;
+                position = (position.0 + direction.0, position.1 + direction.1);
+            }
+            break;
         }
 
-        force_loop_obstacle_locations
+        cells[index(obstacle)] = turn;
     }
 
-    fn alternate(&self, new_obstacle_index: usize) -> Self {
-        let starting_guard = self.starting_guard.clone();
+    let mut text = String::with_capacity(size * (size + 1));
 
-        let mut obstacle_indices = self.obstacle_indices.clone();
-        obstacle_indices.push(new_obstacle_index);
-        obstacle_indices.sort();
+    for (index, &cell) in cells.iter().enumerate() {
+        if index > 0 && index % size == 0 {
+            text.push('\n');
+        }
 
-        let grid = self.grid.clone();
+        let scattered = random.below(100) < obstacle_percent;
 
-        Self { starting_guard, obstacle_indices, grid }
+        if index == guard.0 as usize * size + guard.1 as usize {
+            text.push_str(GUARD_POINTING_NORTH);
+        } else if cell == turn || (cell != corridor && scattered) {
+            text.push_str(OBSTACLE);
+        } else {
+            text.push_str(EMPTY_SPACE);
+        }
     }
+
+    text
 }
 
 #[derive(Clone)]
@@ -227,6 +519,7 @@ struct Guard {
 }
 
 const GUARD_POINTING_NORTH: &'static str = "^";
+const GUARD_MARKERS: &[char] = &['^', '>', 'v', '<'];
 const EMPTY_SPACE: &'static str = ".";
 const OBSTACLE: &'static str = "#";
 
@@ -234,14 +527,22 @@ impl From<&str> for Map {
     fn from(input: &str) -> Self {
         let (mut flattened_input, grid) = Grid::parse_input(input);
 
-        let guard_index = flattened_input.find(GUARD_POINTING_NORTH).expect("Guard should exist on the map pointing North");
-        flattened_input.replace_range(guard_index..guard_index + 1, EMPTY_SPACE);
+        let guards = flattened_input
+            .match_indices(GUARD_MARKERS)
+            .map(|(index, marker)| Guard { index, direction: Direction::from(marker.chars().next().unwrap()) })
+            .collect::<Vec<_>>();
 
-        let starting_guard = Guard { index: guard_index, direction: Direction::North };
+        assert!(!guards.is_empty(), "At least one guard should exist on the map, pointing one of ^>v<");
+
+        for guard in guards.iter() {
+            flattened_input.replace_range(guard.index..guard.index + 1, EMPTY_SPACE);
+        }
 
         let obstacle_indices = flattened_input.match_indices(OBSTACLE).map(|(i, _)| i).collect::<Vec<_>>();
 
-        Self { starting_guard, obstacle_indices, grid }
+        let jump_table = JumpTable::new(&grid, &obstacle_indices);
+
+        Self { guards, jump_table, grid }
     }
 }
 
@@ -249,6 +550,12 @@ impl From<&str> for Map {
 mod test {
     use super::*;
 
+    fn obstacle_indices(map: &Map) -> Vec<usize> {
+        (0..(map.grid.row_count * map.grid.column_count) as usize)
+            .filter(|&index| map.jump_table.is_obstacle(index))
+            .collect()
+    }
+
     const EXAMPLE: &'static str = r"
 ....#.....
 .........#
@@ -265,11 +572,11 @@ mod test {
     fn map_example() {
         let map = Map::from(EXAMPLE);
 
-        let guard_starting_position = map.grid.get_position(map.starting_guard.index).unwrap();
+        let guard_starting_position = map.grid.get_position(map.starting_guard().index).unwrap();
 
         println!("Guard starting position ({}, {})", guard_starting_position.0, guard_starting_position.1);
 
-        for obstacle_index in map.obstacle_indices.iter() {
+        for obstacle_index in obstacle_indices(&map).iter() {
             let obstacle_postion = map.grid.get_position(*obstacle_index).unwrap();
 
             println!("Obstacle position ({}, {})", obstacle_postion.0, obstacle_postion.1);
@@ -292,8 +599,206 @@ mod test {
     fn forced_loop_example() {
         let map = Map::from(EXAMPLE);
 
-        let locations = map.force_loop_locations();
+        let locations = map.force_loop_locations(Evaluation::Sequential);
 
         assert_eq!(locations.len(), 6);
+
+        let parallel_locations = map.force_loop_locations(Evaluation::Parallel(4));
+
+        assert_eq!(parallel_locations, locations);
+    }
+
+    #[test]
+    fn jump_table_add_obstacle() {
+        let map = Map::from(EXAMPLE);
+
+        let start = map.starting_guard().index;
+        let new_obstacle = map.grid.get_index(&Position(6, 3)).unwrap();
+
+        assert_eq!(map.jump_table.next_obstacle(start, Direction::North), Some(map.grid.get_index(&Position(0, 4)).unwrap()));
+        assert_eq!(map.jump_table.next_obstacle(start, Direction::West), Some(map.grid.get_index(&Position(6, 1)).unwrap()));
+        assert_eq!(map.jump_table.next_obstacle(start, Direction::South), None);
+
+        let mut alternate_map = map.clone();
+        alternate_map.add_obstacle(new_obstacle);
+
+        assert_eq!(alternate_map.jump_table.next_obstacle(start, Direction::West), Some(new_obstacle));
+
+        let rebuilt = JumpTable::new(&alternate_map.grid, &obstacle_indices(&alternate_map));
+
+        for index in 0..(map.grid.row_count * map.grid.column_count) as usize {
+            for &direction in Direction::orthogonal() {
+                assert_eq!(
+                    alternate_map.jump_table.next_obstacle(index, direction),
+                    rebuilt.next_obstacle(index, direction),
+                    "{index} {direction}",
+                );
+            }
+        }
+
+        assert!(matches!(alternate_map.predict_turns(), MovementEnd::Loop(_)));
+        assert_eq!(map.predict_turns(), MovementEnd::Exit);
+
+        // taking the obstacle away again restores the original table
+        alternate_map.remove_obstacle(new_obstacle);
+
+        for index in 0..(map.grid.row_count * map.grid.column_count) as usize {
+            for &direction in Direction::orthogonal() {
+                assert_eq!(alternate_map.jump_table.next_obstacle(index, direction), map.jump_table.next_obstacle(index, direction));
+            }
+        }
+    }
+
+    #[test]
+    fn loop_details_example() {
+        let map = Map::from(EXAMPLE);
+
+        let mut alternate_map = map.clone();
+        alternate_map.add_obstacle(map.grid.get_index(&Position(6, 3)).unwrap());
+
+        let (guard_indices, _, movement_end) = alternate_map.predict_guard();
+
+        let MovementEnd::Loop(guard_loop) = movement_end else {
+            panic!("Obstruction at (6, 3) should cause a loop");
+        };
+
+        assert_eq!(alternate_map.predict_turns(), MovementEnd::Loop(guard_loop.clone()));
+
+        assert_eq!(map.grid.get_position(guard_loop.entry_index).unwrap(), Position(6, 4));
+        assert_eq!(guard_loop.entry_direction, Direction::North);
+        assert_eq!(guard_loop.length, 18);
+        assert_eq!(guard_loop.cells.len(), 18);
+        assert!(guard_indices.len() > guard_loop.length);
+    }
+
+    #[test]
+    fn loop_entry_mid_line() {
+        let map = Map::from(r"
+.#....
+.....#
+......
+#.....
+....#.
+.^....
+        ");
+
+        let (_, _, movement_end) = map.predict_guard();
+
+        let MovementEnd::Loop(guard_loop) = movement_end else {
+            panic!("Guard should loop");
+        };
+
+        assert_eq!(map.predict_turns(), MovementEnd::Loop(guard_loop.clone()));
+
+        assert_eq!(map.grid.get_position(guard_loop.entry_index).unwrap(), Position(3, 1));
+        assert_eq!(guard_loop.entry_direction, Direction::North);
+        assert_eq!(guard_loop.length, 10);
+        assert_eq!(guard_loop.cells.len(), 10);
+
+        let loop_positions = guard_loop.cells.iter().map(|&i| map.grid.get_position(i).unwrap()).collect::<Vec<_>>();
+
+        assert_eq!(loop_positions[0], Position(1, 1));
+        assert!(!loop_positions.contains(&Position(4, 1)));
+    }
+
+    #[test]
+    fn parallel_generated_map() {
+        let map = Map::from(generate_map(60, 5, 42).as_str());
+
+        // the spiral corridor keeps the base patrol exiting after a long walk
+        assert_eq!(map.predict_guard().2, MovementEnd::Exit);
+        assert!(map.loop_candidates().len() > 60 * 60 / 5);
+
+        let sequential = map.force_loop_locations(Evaluation::Sequential);
+
+        assert!(!sequential.is_empty());
+
+        for thread_count in [1, 3, 8] {
+            assert_eq!(map.force_loop_locations(Evaluation::Parallel(thread_count)), sequential);
+        }
+    }
+
+    #[test]
+    fn jump_table_overlay() {
+        let map = Map::from(generate_map(20, 10, 7).as_str());
+
+        let cell_count = (map.grid.row_count * map.grid.column_count) as usize;
+
+        for extra_obstacle in (0..cell_count).step_by(7) {
+            let mut added = map.jump_table.clone();
+            added.add_obstacle(extra_obstacle);
+
+            for index in 0..cell_count {
+                for &direction in Direction::orthogonal() {
+                    if index == extra_obstacle {
+                        continue;
+                    }
+                    assert_eq!(
+                        map.jump_table.next_obstacle_with(index, direction, extra_obstacle),
+                        added.next_obstacle(index, direction),
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn multiple_guards() {
+        let map = Map::from(r"
+.....
+>...<
+.....
+.><..
+.....
+        ");
+
+        assert_eq!(map.guards.len(), 4);
+        assert_eq!(map.guards[0].direction, Direction::East);
+        assert_eq!(map.guards[1].direction, Direction::West);
+
+        let report = map.simulate_patrol(&[TurnPolicy::from("right")]);
+
+        let ends = report.guards.iter().map(|g| g.end).collect::<Vec<_>>();
+        let coverage = report.guards.iter().map(|g| g.coverage.len()).collect::<Vec<_>>();
+
+        assert_eq!(ends, vec![PatrolEnd::Exit(5), PatrolEnd::Exit(5), PatrolEnd::Exit(4), PatrolEnd::Exit(3)]);
+        assert_eq!(coverage, vec![5, 5, 4, 3]);
+        assert_eq!(report.combined_coverage().len(), 10);
+        assert_eq!(report.collisions, vec![
+            Collision { tick: 1, index: map.grid.get_index(&Position(3, 2)).unwrap(), guards: vec![2, 3], kind: CollisionKind::Swap },
+            Collision { tick: 2, index: map.grid.get_index(&Position(1, 2)).unwrap(), guards: vec![0, 1], kind: CollisionKind::SameCell },
+        ]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    #[should_panic(expected = "The guard puzzle needs exactly one guard - found 2")]
+    fn puzzle_rejects_several_guards() {
+        Map::from(">...\n...<").predict_guard();
+    }
+
+    #[test]
+    fn turn_policies() {
+        let map = Map::from(r"
+..#..
+.....
+..^..
+.....
+..#..
+        ");
+
+        let report = map.simulate_patrol(&[TurnPolicy::from("reverse")]);
+
+        assert_eq!(report.guards[0].end, PatrolEnd::Loop(6));
+        assert_eq!(report.guards[0].coverage.len(), 3);
+
+        let report = map.simulate_patrol(&[TurnPolicy::from("left")]);
+
+        assert_eq!(report.guards[0].end, PatrolEnd::Exit(5));
+        assert_eq!(report.guards[0].coverage.len(), 4);
+
+        let report = map.simulate_patrol(&[TurnPolicy::from("BR")]);
+
+        assert_eq!(report.guards[0].end, PatrolEnd::Exit(8));
+        assert_eq!(report.guards[0].coverage.len(), 5);
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashSet;
+
+use utils::Direction;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Turn {
+    Right,
+    Left,
+    Reverse,
+}
+
+impl Turn {
+    pub fn apply(&self, direction: Direction) -> Direction {
+        match *self {
+            Turn::Right => direction.clockwise_orthogonal(),
+            Turn::Left => direction.orthogonal_previous(),
+            Turn::Reverse => direction.orthogonal_flip(),
+        }
+    }
+}
+
+impl From<char> for Turn {
+    fn from(c: char) -> Self {
+        match c {
+            'R' => Turn::Right,
+            'L' => Turn::Left,
+            'B' => Turn::Reverse,
+            _ => panic!("Unexpected turn char '{c}' - expected R, L, or B")
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TurnPolicy {
+    Always(Turn),
+    Scripted(Vec<Turn>),
+}
+
+impl TurnPolicy {
+    pub fn turn(&self, direction: Direction, turn_count: usize) -> Direction {
+        match self {
+            TurnPolicy::Always(turn) => turn.apply(direction),
+            TurnPolicy::Scripted(turns) => turns[turn_count % turns.len()].apply(direction),
+        }
+    }
+
+    // how many turns it takes before the policy repeats itself
+    pub fn period(&self) -> usize {
+        match self {
+            TurnPolicy::Always(_) => 1,
+            TurnPolicy::Scripted(turns) => turns.len(),
+        }
+    }
+}
+
+impl From<&str> for TurnPolicy {
+    fn from(text: &str) -> Self {
+        match text {
+            "right" => TurnPolicy::Always(Turn::Right),
+            "left" => TurnPolicy::Always(Turn::Left),
+            "reverse" => TurnPolicy::Always(Turn::Reverse),
+            script => {
+                let turns = script.chars().map(Turn::from).collect::<Vec<_>>();
+                assert!(!turns.is_empty(), "Scripted turn policy requires at least one turn");
+                TurnPolicy::Scripted(turns)
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatrolEnd {
+    Exit(usize),
+    Loop(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct GuardPatrol {
+    pub start_index: usize,
+    pub start_direction: Direction,
+    pub coverage: HashSet<usize>,
+    pub end: PatrolEnd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionKind {
+    SameCell,
+    Swap,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    pub tick: usize,
+    pub index: usize,
+    pub guards: Vec<usize>,
+    pub kind: CollisionKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct PatrolReport {
+    pub ticks: usize,
+    pub guards: Vec<GuardPatrol>,
+    pub collisions: Vec<Collision>,
+}
+
+impl PatrolReport {
+    pub fn combined_coverage(&self) -> HashSet<usize> {
+        self.guards
+            .iter()
+            .flat_map(|g| g.coverage.iter().copied())
+            .collect()
+    }
+}
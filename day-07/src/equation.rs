@@ -1,4 +1,44 @@
-use crate::operator::{Operator, OperatorBinaryIterator, OperatorTrinaryIterator};
+use std::{fmt, ops::RangeInclusive};
+
+use crate::operator::{EquationOperator, Operator, OperatorBinaryIterator, OperatorTrinaryIterator, Undo};
+
+pub const BINARY_OPERATORS: &[&dyn EquationOperator] = &[&Operator::Add, &Operator::Multiply];
+pub const TRINARY_OPERATORS: &[&dyn EquationOperator] = &[&Operator::Add, &Operator::Multiply, &Operator::Concatenate];
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SolveMode {
+    First,
+    All,
+}
+
+pub struct Solution<'a> {
+    pub values: &'a [u64],
+    pub operators: Vec<&'a dyn EquationOperator>,
+}
+
+impl fmt::Display for Solution<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.values[0])?;
+
+        for (operator, value) in self.operators.iter().zip(self.values.iter().skip(1)) {
+            write!(f, " {} {value}", operator.symbol())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Solver {
+    Exhaustive,
+    Backward,
+}
+
+// The operators undone from the end of an equation, and the values the operators before them have to reach.
+struct Undone<'s, 'a> {
+    suffix: &'s [&'a dyn EquationOperator],
+    accepted: RangeInclusive<u64>,
+}
 
 pub struct Equation {
     left_hand_value: u64,
@@ -6,34 +46,20 @@ pub struct Equation {
 }
 
 impl Equation {
-    pub fn test(&self, operators: &[Operator]) -> u64 {
+    // Returns None when the operators overflow, rather than wrapping around to a value that could match by accident
+    pub fn test(&self, operators: &[Operator]) -> Option<u64> {
         let mut values_it = self.right_hand_values.iter();
         let mut operators_it = operators.iter();
 
         let mut result = *values_it.next().unwrap();
 
-        loop {
-            if let Some(value) = values_it.next() {
-                let operator = operators_it.next().unwrap();
-
-                match *operator {
-                    Operator::Add => result += *value,
-                    Operator::Multiply => result *= *value,
-                    Operator::Concatenate => {
-                        let rhs = *value as u32;
-                        
-                        let rhs_digit_count = rhs.ilog10();
-                        let lhs_multiplier = 10_u32.pow(rhs_digit_count + 1);
-
-                        result = result * (lhs_multiplier as u64) + (rhs as u64);
-                    },
-                }
-            } else {
-                break;
-            }
+        for value in values_it {
+            let operator = operators_it.next().unwrap();
+
+            result = operator.apply(result, *value)?;
         }
 
-        result
+        Some(result)
     }
 
     pub fn valid_binary(&self) -> bool {
@@ -48,11 +74,113 @@ impl Equation {
         self.valid(operator_it)
     }
 
+    pub fn valid_backward(&self, operators: &[&dyn EquationOperator]) -> bool {
+        !self.solve(operators, SolveMode::First).is_empty()
+    }
+
+    // Undoes the operators from the last value back to the first, so a branch is dropped as soon as the remaining
+    // values can no longer produce what is left of the left hand value.
+    pub fn solve<'a>(&'a self, operators: &[&'a dyn EquationOperator], mode: SolveMode) -> Vec<Solution<'a>> {
+        let mut solutions = vec![];
+
+        self.solve_backward(self.left_hand_value, self.right_hand_values.len(), operators, &mut vec![], &mut solutions, mode);
+
+        solutions
+    }
+
+    fn solve_backward<'a>(
+        &'a self,
+        target: u64,
+        value_count: usize,
+        operators: &[&'a dyn EquationOperator],
+        suffix: &mut Vec<&'a dyn EquationOperator>,
+        solutions: &mut Vec<Solution<'a>>,
+        mode: SolveMode,
+    ) {
+        let value = self.right_hand_values[value_count - 1];
+
+        if value_count == 1 {
+            if target == value {
+                solutions.push(self.solution(&[], suffix));
+            }
+            return;
+        }
+
+        for &operator in operators {
+            if mode == SolveMode::First && !solutions.is_empty() {
+                return;
+            }
+
+            suffix.push(operator);
+
+            match operator.undo(target, value) {
+                Undo::Previous(previous) => {
+                    self.solve_backward(previous, value_count - 1, operators, suffix, solutions, mode);
+                },
+                Undo::Between(lowest, highest) => {
+                    // the range can be as wide as the value, so the values before it are evaluated instead
+                    let undone = Undone { suffix, accepted: lowest..=highest };
+                    self.solve_forward(self.right_hand_values[0], operators, &mut vec![], &undone, solutions, mode);
+                },
+                Undo::Any => {
+                    let undone = Undone { suffix, accepted: 0..=u64::MAX };
+                    self.solve_forward(self.right_hand_values[0], operators, &mut vec![], &undone, solutions, mode);
+                },
+                Undo::Impossible => {},
+            }
+
+            suffix.pop();
+        }
+    }
+
+    // Used when an operator can be undone to any value in a range - every operator sequence for the values before it
+    // that evaluates to a value in the range is a solution.
+    fn solve_forward<'a>(
+        &'a self,
+        result: u64,
+        operators: &[&'a dyn EquationOperator],
+        prefix: &mut Vec<&'a dyn EquationOperator>,
+        undone: &Undone<'_, 'a>,
+        solutions: &mut Vec<Solution<'a>>,
+        mode: SolveMode,
+    ) {
+        let index = prefix.len() + 1;
+
+        if index + undone.suffix.len() == self.right_hand_values.len() {
+            if undone.accepted.contains(&result) {
+                solutions.push(self.solution(prefix, undone.suffix));
+            }
+            return;
+        }
+
+        for &operator in operators {
+            if mode == SolveMode::First && !solutions.is_empty() {
+                return;
+            }
+
+            if let Some(next) = operator.apply(result, self.right_hand_values[index]) {
+                prefix.push(operator);
+                self.solve_forward(next, operators, prefix, undone, solutions, mode);
+                prefix.pop();
+            }
+        }
+    }
+
+    fn solution<'a>(&'a self, prefix: &[&'a dyn EquationOperator], suffix: &[&'a dyn EquationOperator]) -> Solution<'a> {
+        let operators = prefix
+            .iter()
+            .chain(suffix.iter().rev())
+            .copied()
+            .collect();
+
+        Solution { values: &self.right_hand_values, operators }
+    }
+
     fn valid(&self, operator_it: impl Iterator<Item=Vec<Operator>>) -> bool {
         for operators in operator_it {
             let value = self.test(&operators);
 
-            if value == self.left_hand_value {
+            if value == Some(self.left_hand_value) {
                 return true;
             }
         }
@@ -61,6 +189,14 @@ impl Equation {
     }
 }
 
+impl fmt::Display for Equation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values = self.right_hand_values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+
+        write!(f, "{}: {}", self.left_hand_value, values.join(" "))
+    }
+}
+
 impl From<&str> for Equation {
     fn from(value: &str) -> Self {
         let mut split = value.split(":");
@@ -81,16 +217,33 @@ pub struct Calibration {
 }
 
 impl Calibration {
-    pub fn result_binary(&self) -> u64 {
+    pub fn equations(&self) -> &[Equation] {
+        &self.equations
+    }
+
+    pub fn result_with(&self, operators: &[&dyn EquationOperator]) -> u64 {
+        self.equations.iter()
+            .filter(|e| e.valid_backward(operators))
+            .map(|e| e.left_hand_value)
+            .sum()
+    }
+
+    pub fn result_binary(&self, solver: Solver) -> u64 {
         self.equations.iter()
-            .filter(|e| e.valid_binary())
+            .filter(|e| match solver {
+                Solver::Exhaustive => e.valid_binary(),
+                Solver::Backward => e.valid_backward(BINARY_OPERATORS),
+            })
             .map(|e| e.left_hand_value)
             .sum()
     }
 
-    pub fn result_trinary(&self) -> u64 {
+    pub fn result_trinary(&self, solver: Solver) -> u64 {
         self.equations.iter()
-            .filter(|e| e.valid_trinary())
+            .filter(|e| match solver {
+                Solver::Exhaustive => e.valid_trinary(),
+                Solver::Backward => e.valid_backward(TRINARY_OPERATORS),
+            })
             .map(|e| e.left_hand_value)
             .sum()
     }
@@ -115,6 +268,10 @@ impl From<&str> for Calibration {
 
 #[cfg(test)]
 mod tests {
+    use utils::Random;
+
+    use crate::operator::{ConcatenateBase, Divide, Exponent, Subtract};
+
     use super::*;
 
     const EXAMPLE: &'static str = r"
@@ -193,7 +350,11 @@ mod tests {
     fn example_binary() {
         let calibration = Calibration::from(EXAMPLE);
 
-        let result = calibration.result_binary();
+        let result = calibration.result_binary(Solver::Exhaustive);
+
+        assert_eq!(result, 3749);
+
+        let result = calibration.result_binary(Solver::Backward);
 
         assert_eq!(result, 3749);
     }
@@ -202,8 +363,187 @@ mod tests {
     fn examle_trinary() {
         let calibration = Calibration::from(EXAMPLE);
 
-        let result = calibration.result_trinary();
+        let result = calibration.result_trinary(Solver::Exhaustive);
+
+        assert_eq!(result, 11387);
+
+        let result = calibration.result_trinary(Solver::Backward);
 
         assert_eq!(result, 11387);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn backward_matches_exhaustive() {
+        let mut random = Random::new(2024);
+
+        for _ in 0..500 {
+            let value_count = 2 + random.below(5) as usize;
+            let right_hand_values = (0..value_count).map(|_| 1 + random.below(15)).collect::<Vec<_>>();
+
+            // half the targets are built from real operator choices so both valid and invalid equations are covered
+            let left_hand_value = if random.below(2) == 0 {
+                let operators = (1..value_count)
+                    .map(|_| [Operator::Add, Operator::Multiply, Operator::Concatenate][random.below(3) as usize])
+                    .collect::<Vec<_>>();
+                Equation { left_hand_value: 0, right_hand_values: right_hand_values.clone() }.test(&operators).unwrap()
+            } else {
+                1 + random.below(5000)
+            };
+
+            let equation = Equation { left_hand_value, right_hand_values };
+
+            assert_eq!(equation.valid_backward(BINARY_OPERATORS), equation.valid_binary());
+            assert_eq!(equation.valid_backward(TRINARY_OPERATORS), equation.valid_trinary());
+        }
+    }
+
+    #[test]
+    fn backward_multiply_zero() {
+        let equation = Equation::from("0: 7 3 0");
+
+        assert!(equation.valid_backward(BINARY_OPERATORS));
+        assert!(equation.valid_binary());
+
+        let equation = Equation::from("5: 7 3 0");
+
+        assert!(!equation.valid_backward(BINARY_OPERATORS));
+        assert!(!equation.valid_binary());
+    }
+
+    fn all_forward(equation: &Equation, operators: &[&dyn EquationOperator]) -> Vec<String> {
+        let mut results: Vec<(u64, String)> = vec![(equation.right_hand_values[0], equation.right_hand_values[0].to_string())];
+
+        for &value in equation.right_hand_values.iter().skip(1) {
+            results = results
+                .into_iter()
+                .flat_map(|(result, text)| {
+                    operators
+                        .iter()
+                        .filter_map(move |o| o.apply(result, value).map(|r| (r, format!("{text} {} {value}", o.symbol()))))
+                })
+                .collect();
+        }
+
+        let mut expressions = results
+            .into_iter()
+            .filter(|(r, _)| *r == equation.left_hand_value)
+            .map(|(_, text)| text)
+            .collect::<Vec<_>>();
+        expressions.sort();
+
+        expressions
+    }
+
+    #[test]
+    fn solve_expression() {
+        let equation = Equation::from("3267: 81 40 27");
+
+        let solutions = equation.solve(BINARY_OPERATORS, SolveMode::All)
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(solutions, vec!["81 * 40 + 27", "81 + 40 * 27"]);
+
+        let equation = Equation::from("7290: 6 8 6 15");
+
+        let solution = equation.solve(TRINARY_OPERATORS, SolveMode::First);
+
+        assert_eq!(solution.len(), 1);
+        assert_eq!(solution[0].to_string(), "6 * 8 || 6 * 15");
+
+        assert!(Equation::from("83: 17 5").solve(TRINARY_OPERATORS, SolveMode::All).is_empty());
+    }
+
+    #[test]
+    fn solve_custom_operators() {
+        let operators: &[&dyn EquationOperator] = &[
+            &Operator::Add,
+            &Operator::Multiply,
+            &Subtract,
+            &Divide,
+            &Exponent,
+            &ConcatenateBase(2),
+        ];
+
+        let equation = Equation::from("14: 3 2 5");
+
+        let mut solutions = equation.solve(operators, SolveMode::All)
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        solutions.sort();
+
+        assert_eq!(solutions, all_forward(&equation, operators));
+        assert!(solutions.contains(&"3 ^ 2 + 5".to_string()));
+
+        // dividing by a huge value can be undone to ten billion earlier values
+        let equation = Equation::from("0: 5 4 10000000000");
+
+        let mut solutions = equation.solve(operators, SolveMode::All)
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        solutions.sort();
+
+        assert_eq!(solutions, all_forward(&equation, operators));
+        assert!(solutions.contains(&"5 * 4 / 10000000000".to_string()));
+
+        let mut random = Random::new(7);
+
+        for _ in 0..300 {
+            let value_count = 2 + random.below(3) as usize;
+            let right_hand_values = (0..value_count).map(|_| random.below(9)).collect::<Vec<_>>();
+            let left_hand_value = random.below(60);
+
+            let equation = Equation { left_hand_value, right_hand_values };
+
+            let mut solutions = equation.solve(operators, SolveMode::All)
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>();
+            solutions.sort();
+
+            assert_eq!(solutions, all_forward(&equation, operators), "{equation}");
+            assert_eq!(equation.valid_backward(operators), !solutions.is_empty());
+        }
+    }
+
+    #[test]
+    fn values_beyond_u32() {
+        let equation = Equation::from("9876543210123: 9876543210 123");
+
+        assert_eq!(equation.test(&[Operator::Concatenate]), Some(9876543210123));
+        assert!(equation.valid_trinary());
+        assert!(equation.valid_backward(TRINARY_OPERATORS));
+
+        let equation = Equation::from("4294967296000: 4294967296 1000");
+
+        assert_eq!(equation.test(&[Operator::Multiply]), Some(4294967296000));
+        assert!(equation.valid_binary());
+        assert!(equation.valid_backward(BINARY_OPERATORS));
+    }
+
+    #[test]
+    fn overflow_prunes() {
+        // 2^32 * 2^32 wraps around to 0 in u64
+        let equation = Equation::from("0: 4294967296 4294967296");
+
+        assert_eq!(equation.test(&[Operator::Multiply]), None);
+        assert!(!equation.valid_binary());
+        assert!(!equation.valid_backward(BINARY_OPERATORS));
+
+        let equation = Equation::from("18446744073709551615: 18446744073709551615 1 1");
+
+        assert_eq!(equation.test(&[Operator::Add, Operator::Add]), None);
+        assert_eq!(equation.test(&[Operator::Concatenate, Operator::Multiply]), None);
+        assert_eq!(equation.test(&[Operator::Multiply, Operator::Multiply]), Some(u64::MAX));
+        assert!(equation.valid_trinary());
+        assert!(equation.valid_backward(TRINARY_OPERATORS));
+
+        let equation = Equation::from("12: 18446744073709551615 1 12");
+
+        assert!(!equation.valid_trinary());
+        assert!(!equation.valid_backward(TRINARY_OPERATORS));
+    }
+}
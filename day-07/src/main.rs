@@ -1,4 +1,5 @@
-use equation::Calibration;
+use equation::{Calibration, SolveMode, Solver, BINARY_OPERATORS, TRINARY_OPERATORS};
+use operator::{operator_from_symbol, EquationOperator};
 use utils::{part_selection, Part, Timer};
 
 mod equation;
@@ -11,24 +12,90 @@ fn main() {
 
     let part = part_selection();
 
+    if let Some(symbols) = option_value("--operators") {
+        let operators = symbols.split_whitespace().map(operator_from_symbol).collect::<Vec<_>>();
+        let operators = operators.iter().map(|o| o.as_ref()).collect::<Vec<_>>();
+        custom(&text, &operators);
+        return;
+    }
+
+    let solver = solver_selection();
+
     match part {
-        Part::One => part_1(&text),
-        Part::Two => part_2(&text),
+        Part::One => part_1(&text, solver),
+        Part::Two => part_2(&text, solver),
     }
 }
 
-fn part_1(input: &str) {
+fn part_1(input: &str, solver: Solver) {
     let calibration = Calibration::from(input);
 
-    let result = calibration.result_binary();
+    let result = calibration.result_binary(solver);
+
+    show_solutions(&calibration, BINARY_OPERATORS);
 
     println!("{result}");
 }
 
-fn part_2(input: &str) {
+fn part_2(input: &str, solver: Solver) {
     let calibration = Calibration::from(input);
 
-    let result = calibration.result_trinary();
+    let result = calibration.result_trinary(solver);
+
+    show_solutions(&calibration, TRINARY_OPERATORS);
 
     println!("{result}");
 }
+
+fn custom(input: &str, operators: &[&dyn EquationOperator]) {
+    let calibration = Calibration::from(input);
+
+    let result = calibration.result_with(operators);
+
+    show_solutions(&calibration, operators);
+
+    println!("{result}");
+}
+
+fn show_solutions(calibration: &Calibration, operators: &[&dyn EquationOperator]) {
+    let mode = match option_value("--solve").as_deref() {
+        Some("first") => SolveMode::First,
+        Some("all") => SolveMode::All,
+        Some(mode) => panic!("Unknown solve mode '{mode}' - expected 'first' or 'all'"),
+        None => return,
+    };
+
+    for equation in calibration.equations() {
+        let solutions = equation.solve(operators, mode);
+
+        if solutions.is_empty() {
+            println!("{equation} has no solution");
+        }
+
+        for solution in solutions {
+            println!("{equation} = {solution}");
+        }
+    }
+}
+
+fn option_value(name: &str) -> Option<String> {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    args.iter()
+        .position(|a| a == name)
+        .map(|i| args.get(i + 1).unwrap_or_else(|| panic!("Missing value for '{name}'")).clone())
+}
+
+fn solver_selection() -> Solver {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    if args.len() > 2 {
+        match args[2].as_str() {
+            "--exhaustive" => return Solver::Exhaustive,
+            "--backward" => return Solver::Backward,
+            _ => {}
+        }
+    }
+
+    Solver::Exhaustive
+}
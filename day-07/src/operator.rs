@@ -5,6 +5,182 @@ pub enum Operator {
     Concatenate,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Undo {
+    Previous(u64),
+    Between(u64, u64),
+    Any,
+    Impossible,
+}
+
+// Operators are always applied left to right. `apply` returns None when the result is undefined, which drops that
+// operator sequence. `undo` works out what the running value had to be before the operator was applied with `value`
+// to give `result`.
+pub trait EquationOperator {
+    fn symbol(&self) -> String;
+    fn apply(&self, lhs: u64, value: u64) -> Option<u64>;
+    fn undo(&self, result: u64, value: u64) -> Undo;
+}
+
+impl EquationOperator for Operator {
+    fn symbol(&self) -> String {
+        match *self {
+            Operator::Add => "+".to_string(),
+            Operator::Multiply => "*".to_string(),
+            Operator::Concatenate => "||".to_string(),
+        }
+    }
+
+    fn apply(&self, lhs: u64, value: u64) -> Option<u64> {
+        match *self {
+            Operator::Add => lhs.checked_add(value),
+            Operator::Multiply => lhs.checked_mul(value),
+            Operator::Concatenate => ConcatenateBase(10).apply(lhs, value),
+        }
+    }
+
+    fn undo(&self, result: u64, value: u64) -> Undo {
+        match *self {
+            Operator::Add => match result.checked_sub(value) {
+                Some(previous) => Undo::Previous(previous),
+                None => Undo::Impossible,
+            },
+            Operator::Multiply => {
+                if value == 0 {
+                    if result == 0 {
+                        Undo::Any
+                    } else {
+                        Undo::Impossible
+                    }
+                } else if result.is_multiple_of(value) {
+                    Undo::Previous(result / value)
+                } else {
+                    Undo::Impossible
+                }
+            },
+            Operator::Concatenate => ConcatenateBase(10).undo(result, value),
+        }
+    }
+}
+
+pub struct Subtract;
+
+impl EquationOperator for Subtract {
+    fn symbol(&self) -> String {
+        "-".to_string()
+    }
+
+    fn apply(&self, lhs: u64, value: u64) -> Option<u64> {
+        lhs.checked_sub(value)
+    }
+
+    fn undo(&self, result: u64, value: u64) -> Undo {
+        match result.checked_add(value) {
+            Some(previous) => Undo::Previous(previous),
+            None => Undo::Impossible,
+        }
+    }
+}
+
+pub struct Divide;
+
+impl EquationOperator for Divide {
+    fn symbol(&self) -> String {
+        "/".to_string()
+    }
+
+    fn apply(&self, lhs: u64, value: u64) -> Option<u64> {
+        lhs.checked_div(value)
+    }
+
+    fn undo(&self, result: u64, value: u64) -> Undo {
+        if value == 0 {
+            return Undo::Impossible;
+        }
+
+        // integer division rounds down, so every value up to the next multiple divides to the same result
+        match result.checked_mul(value) {
+            Some(lowest) => Undo::Between(lowest, lowest.saturating_add(value - 1)),
+            None => Undo::Impossible,
+        }
+    }
+}
+
+pub struct Exponent;
+
+impl EquationOperator for Exponent {
+    fn symbol(&self) -> String {
+        "^".to_string()
+    }
+
+    fn apply(&self, lhs: u64, value: u64) -> Option<u64> {
+        lhs.checked_pow(u32::try_from(value).ok()?)
+    }
+
+    fn undo(&self, result: u64, value: u64) -> Undo {
+        match value {
+            0 if result == 1 => Undo::Any,
+            0 => Undo::Impossible,
+            1 => Undo::Previous(result),
+            _ => {
+                let estimate = (result as f64).powf(1.0 / value as f64).round() as u64;
+
+                (estimate.saturating_sub(1)..=estimate + 1)
+                    .find(|&root| self.apply(root, value) == Some(result))
+                    .map_or(Undo::Impossible, Undo::Previous)
+            },
+        }
+    }
+}
+
+pub struct ConcatenateBase(pub u64);
+
+impl ConcatenateBase {
+    fn multiplier(&self, value: u64) -> Option<u64> {
+        assert!(self.0 >= 2, "Concatenation requires a base of at least 2 - found {}", self.0);
+
+        let digit_count = value.checked_ilog(self.0).unwrap_or(0) + 1;
+
+        self.0.checked_pow(digit_count)
+    }
+}
+
+impl EquationOperator for ConcatenateBase {
+    fn symbol(&self) -> String {
+        if self.0 == 10 {
+            "||".to_string()
+        } else {
+            format!("||{}", self.0)
+        }
+    }
+
+    fn apply(&self, lhs: u64, value: u64) -> Option<u64> {
+        lhs.checked_mul(self.multiplier(value)?)?.checked_add(value)
+    }
+
+    fn undo(&self, result: u64, value: u64) -> Undo {
+        match self.multiplier(value) {
+            Some(multiplier) if result % multiplier == value => Undo::Previous(result / multiplier),
+            _ => Undo::Impossible,
+        }
+    }
+}
+
+pub fn operator_from_symbol(symbol: &str) -> Box<dyn EquationOperator> {
+    match symbol {
+        "+" => Box::new(Operator::Add),
+        "*" => Box::new(Operator::Multiply),
+        "||" => Box::new(Operator::Concatenate),
+        "-" => Box::new(Subtract),
+        "/" => Box::new(Divide),
+        "^" => Box::new(Exponent),
+        _ => match symbol.strip_prefix("||").map(|b| b.parse::<u64>()) {
+            Some(Ok(base)) => Box::new(ConcatenateBase(base)),
+            _ => panic!("Unknown operator '{symbol}' - expected +, *, ||, -, /, ^, or || followed by a base"),
+        },
+    }
+}
+
 impl Operator {
     fn increment(&mut self) -> bool {
         match *self {
@@ -7,44 +7,166 @@ pub struct Array {
     antenna_indices_map: HashMap<char, Vec<usize>>,
 }
 
-#[derive(Clone, Copy)]
-pub enum AntinodeRule {
-    TwiceDistance,
-    Inline
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineRule {
+    None,
+    Delta,
+    Lattice,
 }
 
-impl Array {
-    pub fn find_all_antinodes(&self, rule: AntinodeRule) -> HashMap<char, Vec<usize>> {
-        let mut antenna_antinodes_map = HashMap::new();
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pairing {
+    SameFrequency,
+    CrossFrequency,
+    All,
+}
+
+// Every antinode lies on the line through a pair of antennas. Distance ratios pick out the points where one antenna
+// is k times further away than the other - beyond the antennas, between them, or both. The line rule adds every
+// point stepped along the line, either by the raw delta between the antennas or by the smallest step that stays
+// on the grid.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AntinodeRule {
+    pub distance_ratios: Vec<u32>,
+    pub outside: bool,
+    pub between: bool,
+    pub line: LineRule,
+    pub pairing: Pairing,
+}
+
+impl AntinodeRule {
+    pub fn twice_distance() -> Self {
+        Self {
+            distance_ratios: vec![2],
+            outside: true,
+            between: false,
+            line: LineRule::None,
+            pairing: Pairing::SameFrequency,
+        }
+    }
 
-        for (antenna, locations) in self.antenna_indices_map.iter() {
-            if locations.len() < 2 {
-                continue;
+    pub fn inline() -> Self {
+        Self {
+            distance_ratios: vec![],
+            outside: false,
+            between: false,
+            line: LineRule::Delta,
+            pairing: Pairing::SameFrequency,
+        }
+    }
+
+    // fractions of the way from the first antenna to the second where the distance ratios hold
+    fn line_fractions(&self) -> Vec<(i32, i32)> {
+        let mut fractions = vec![];
+
+        for &ratio in self.distance_ratios.iter() {
+            let ratio = ratio as i32;
+
+            if self.outside && ratio > 1 {
+                fractions.push((ratio, ratio - 1));
+                fractions.push((-1, ratio - 1));
             }
 
-            let mut antinodes = vec![];
+            if self.between {
+                fractions.push((ratio, ratio + 1));
+                fractions.push((1, ratio + 1));
+            }
+        }
+
+        fractions
+    }
+}
+
+impl From<&str> for AntinodeRule {
+    fn from(text: &str) -> Self {
+        let mut rule = Self {
+            distance_ratios: vec![],
+            outside: false,
+            between: false,
+            line: LineRule::None,
+            pairing: Pairing::SameFrequency,
+        };
+
+        for token in text.split(',').map(|t| t.trim()) {
+            match token {
+                "outside" => rule.outside = true,
+                "between" => rule.between = true,
+                "line" => rule.line = LineRule::Delta,
+                "lattice" => rule.line = LineRule::Lattice,
+                "cross" => rule.pairing = Pairing::CrossFrequency,
+                "all-pairs" => rule.pairing = Pairing::All,
+                ratio => match ratio.strip_suffix(":1").map(|k| k.parse::<u32>()) {
+                    Some(Ok(k)) if k > 0 => rule.distance_ratios.push(k),
+                    _ => panic!("Unknown antinode rule token '{token}' - expected k:1, outside, between, line, lattice, cross, or all-pairs"),
+                },
+            }
+        }
+
+        if !rule.distance_ratios.is_empty() && !rule.outside && !rule.between {
+            rule.outside = true;
+        }
+
+        rule
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Antinode {
+    pub location: usize,
+    pub frequencies: (char, char),
+    pub antennas: (usize, usize),
+}
+
+impl Array {
+    pub fn find_attributed_antinodes(&self, rule: &AntinodeRule) -> Vec<Antinode> {
+        let mut antinodes = vec![];
 
-            let locations_count = locations.len();
+        let mut antennas = self.antenna_indices_map
+            .iter()
+            .flat_map(|(&antenna, locations)| locations.iter().map(move |&l| (antenna, l)))
+            .collect::<Vec<_>>();
+        antennas.sort();
 
-            for a in 0..locations_count - 1 {
-                for b in a + 1..locations_count {
-                    let pair = (locations[a], locations[b]);
+        let antenna_count = antennas.len();
 
-                    let pair_nodes = find_antinodes(&self.grid, rule, pair);
+        for a in 0..antenna_count {
+            for b in a + 1..antenna_count {
+                let ((antenna_a, location_a), (antenna_b, location_b)) = (antennas[a], antennas[b]);
 
-                    antinodes.extend(pair_nodes);
+                let paired = match rule.pairing {
+                    Pairing::SameFrequency => antenna_a == antenna_b,
+                    Pairing::CrossFrequency => antenna_a != antenna_b,
+                    Pairing::All => true,
+                };
+
+                if !paired {
+                    continue;
                 }
-            }
 
-            if antinodes.len() > 0 {
-                antenna_antinodes_map.insert(*antenna, antinodes);
+                for location in find_antinodes(&self.grid, rule, (location_a, location_b)) {
+                    antinodes.push(Antinode {
+                        location,
+                        frequencies: (antenna_a, antenna_b),
+                        antennas: (location_a, location_b),
+                    });
+                }
             }
         }
 
+        antinodes
+    }
+
+    pub fn find_all_antinodes(&self, rule: &AntinodeRule) -> HashMap<(char, char), Vec<usize>> {
+        let mut antenna_antinodes_map: HashMap<(char, char), Vec<usize>> = HashMap::new();
+
+        for antinode in self.find_attributed_antinodes(rule) {
+            antenna_antinodes_map.entry(antinode.frequencies).or_default().push(antinode.location);
+        }
+
         antenna_antinodes_map
     }
 
-    pub fn find_unique_antinode_locations(&self, rule: AntinodeRule) -> Vec<usize> {
+    pub fn find_unique_antinode_locations(&self, rule: &AntinodeRule) -> Vec<usize> {
         let antenna_antinodes_map = self.find_all_antinodes(rule);
 
         let mut unique_locations = HashSet::new();
@@ -55,9 +177,54 @@ impl Array {
 
         unique_locations.into_iter().collect::<Vec<_>>()
     }
+
+    // how many antenna pairs put an antinode on each cell
+    pub fn coverage(antinodes: &[Antinode]) -> HashMap<usize, usize> {
+        let mut coverage = HashMap::new();
+
+        for antinode in antinodes {
+            *coverage.entry(antinode.location).or_default() += 1;
+        }
+
+        coverage
+    }
+
+    pub fn antenna_at(&self, index: usize) -> Option<char> {
+        self.antenna_indices_map
+            .iter()
+            .find(|(_, locations)| locations.contains(&index))
+            .map(|(&antenna, _)| antenna)
+    }
+
+    pub fn position(&self, index: usize) -> Position {
+        self.grid.get_position(index).unwrap()
+    }
+
+    // antennas are drawn over any antinode sharing their cell, as in the puzzle text
+    pub fn render(&self, antinodes: &[Antinode]) -> String {
+        let size = (self.grid.row_count * self.grid.column_count) as usize;
+
+        let mut cells = vec![EMPTY_SPACE; size];
+
+        for antinode in antinodes {
+            cells[antinode.location] = ANTINODE;
+        }
+
+        for (&antenna, locations) in self.antenna_indices_map.iter() {
+            for &location in locations {
+                cells[location] = antenna;
+            }
+        }
+
+        cells
+            .chunks(self.grid.column_count as usize)
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
-fn find_antinodes(grid: &Grid, rule: AntinodeRule, pair: (usize, usize)) -> Vec<usize> {
+fn find_antinodes(grid: &Grid, rule: &AntinodeRule, pair: (usize, usize)) -> Vec<usize> {
     let a = pair.0.min(pair.1);
     let b = pair.0.max(pair.1);
     
@@ -69,56 +236,71 @@ fn find_antinodes(grid: &Grid, rule: AntinodeRule, pair: (usize, usize)) -> Vec<
 
     let mut antinodes = vec![];
 
-    match rule {
-        AntinodeRule::TwiceDistance => {
-            let antinode_a_side = Position(a_position.0 - delta_row, a_position.1 - delta_column);
-            if grid.validate_position(&antinode_a_side, false) {
-                let antinode_a_side = grid.get_index(&antinode_a_side).unwrap();
-                antinodes.push(antinode_a_side);
-            }
-            
-            let anti_node_b_side = Position(b_position.0 + delta_row, b_position.1 + delta_column);
-            if grid.validate_position(&anti_node_b_side, false) {
-                let antinode_b_side = grid.get_index(&anti_node_b_side).unwrap();
-                antinodes.push(antinode_b_side);
+    let mut add_antinode = |position: &Position| {
+        if let Ok(index) = grid.get_index(position) {
+            if !antinodes.contains(&index) {
+                antinodes.push(index);
             }
+        }
+    };
+
+    for (numerator, denominator) in rule.line_fractions() {
+        // only points that land exactly on the grid count
+        if (delta_row * numerator) % denominator == 0 && (delta_column * numerator) % denominator == 0 {
+            add_antinode(&Position(
+                a_position.0 + delta_row * numerator / denominator,
+                a_position.1 + delta_column * numerator / denominator,
+            ));
+        }
+    }
+
+    let step = match rule.line {
+        LineRule::None => None,
+        LineRule::Delta => Some((delta_row, delta_column)),
+        LineRule::Lattice => {
+            let divisor = gcd(delta_row.unsigned_abs(), delta_column.unsigned_abs()).max(1) as i32;
+            Some((delta_row / divisor, delta_column / divisor))
         },
-        AntinodeRule::Inline => {
-            let mut current_position = a_position;
+    };
 
-            // go 'backwards' until we go off the grid
+    if let Some((step_row, step_column)) = step {
+        let mut current_position = a_position;
 
-            loop {
-                current_position = Position(current_position.0 - delta_row, current_position.1 - delta_column);
+        // go 'backwards' until we go off the grid
 
-                if !grid.validate_position(&current_position, false) {
-                    break;
-                }
-            }
+        while grid.validate_position(&current_position, false) {
+            current_position = Position(current_position.0 - step_row, current_position.1 - step_column);
+        }
 
-            /*
-             * from that starting position follow the line until we go off the grid on the other side
-             * this will automatically include the antenna locations themselves
-             */
+        /*
+         * from that starting position follow the line until we go off the grid on the other side
+         * this will automatically include the antenna locations themselves
+         */
 
-            loop {
-                current_position = Position(current_position.0 + delta_row, current_position.1 + delta_column);
+        loop {
+            current_position = Position(current_position.0 + step_row, current_position.1 + step_column);
 
-                if let Ok(current_index) = grid.get_index(&current_position) {
-                    antinodes.push(current_index);
-                } else {
-                    break;
-                }
+            if grid.validate_position(&current_position, false) {
+                add_antinode(&current_position);
+            } else {
+                break;
             }
         }
     }
 
-    // TODO: antinodes can exist in between antenna if they are far enough apart
-
     antinodes
 }
 
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
 const EMPTY_SPACE: char = '.';
+const ANTINODE: char = '#';
 
 impl From<&str> for Array {
     fn from(input: &str) -> Self {
@@ -160,7 +342,7 @@ mod tests {
     fn example_twice_distance() {
         let array = Array::from(EXAMPLE);
 
-        let locations = array.find_unique_antinode_locations(AntinodeRule::TwiceDistance);
+        let locations = array.find_unique_antinode_locations(&AntinodeRule::twice_distance());
 
         assert_eq!(14, locations.len());
     }
@@ -169,8 +351,127 @@ mod tests {
     fn example_inline() {
         let array = Array::from(EXAMPLE);
 
-        let locations = array.find_unique_antinode_locations(AntinodeRule::Inline);
+        let locations = array.find_unique_antinode_locations(&AntinodeRule::inline());
 
         assert_eq!(34, locations.len());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn between_antennas() {
+        let array = Array::from(r"
+..........
+.a........
+..........
+..........
+.......a..
+..........
+        ");
+
+        let rule = AntinodeRule::from("2:1, between");
+
+        let mut locations = array.find_unique_antinode_locations(&rule);
+        locations.sort();
+
+        let positions = locations.iter().map(|&l| array.grid.get_position(l).unwrap()).collect::<Vec<_>>();
+
+        assert_eq!(positions, vec![Position(2, 3), Position(3, 5)]);
+
+        let rule = AntinodeRule::from("3:1, outside, between");
+
+        let locations = array.find_unique_antinode_locations(&rule);
+
+        // 3:1 between lands a quarter of the way along - off the grid points for a 3 by 6 delta
+        assert!(locations.is_empty());
+    }
+
+    #[test]
+    fn lattice_points() {
+        let array = Array::from(r"
+a.........
+..........
+....a.....
+..........
+..........
+        ");
+
+        let delta = array.find_unique_antinode_locations(&AntinodeRule::inline());
+        let lattice = array.find_unique_antinode_locations(&AntinodeRule::from("lattice"));
+
+        assert_eq!(delta.len(), 3);
+        assert_eq!(lattice.len(), 5);
+        assert!(lattice.contains(&array.grid.get_index(&Position(1, 2)).unwrap()));
+    }
+
+    #[test]
+    fn cross_frequency() {
+        let array = Array::from(r"
+......
+.a....
+......
+...b..
+......
+......
+        ");
+
+        assert!(array.find_unique_antinode_locations(&AntinodeRule::twice_distance()).is_empty());
+
+        let antinodes = array.find_all_antinodes(&AntinodeRule::from("2:1, cross"));
+
+        assert_eq!(antinodes.len(), 1);
+        assert_eq!(antinodes[&('a', 'b')], vec![array.grid.get_index(&Position(5, 5)).unwrap()]);
+    }
+
+    #[test]
+    fn attribution_and_coverage() {
+        let array = Array::from(r"
+..........
+..........
+..........
+....a.....
+..........
+.....a....
+..........
+..........
+..........
+..........
+        ");
+
+        let antinodes = array.find_attributed_antinodes(&AntinodeRule::twice_distance());
+
+        assert_eq!(antinodes.len(), 2);
+        assert!(antinodes.iter().all(|a| a.frequencies == ('a', 'a') && a.antennas == (34, 55)));
+
+        let array = Array::from(EXAMPLE);
+
+        let antinodes = array.find_attributed_antinodes(&AntinodeRule::twice_distance());
+        let coverage = Array::coverage(&antinodes);
+
+        assert_eq!(coverage.len(), 14);
+        assert_eq!(coverage.values().sum::<usize>(), antinodes.len());
+        assert!(coverage.values().any(|&c| c > 1));
+    }
+
+    #[test]
+    fn render_example() {
+        let array = Array::from(EXAMPLE);
+
+        let antinodes = array.find_attributed_antinodes(&AntinodeRule::twice_distance());
+
+        let expected = r"
+......#....#
+...#....0...
+....#0....#.
+..#....0....
+....0....#..
+.#....A.....
+...#........
+#......#....
+........A...
+.........A..
+..........#.
+..........#.
+        ";
+
+        assert_eq!(array.render(&antinodes), expected.trim());
+    }
+}
@@ -1,4 +1,4 @@
-use array::Array;
+use array::{AntinodeRule, Array};
 use utils::{part_selection, Part, Timer};
 
 mod array;
@@ -19,15 +19,86 @@ fn main() {
 fn part_1(input: &str) {
     let array = Array::from(input);
 
-    let locations = array.find_unique_antinode_locations(array::AntinodeRule::TwiceDistance);
+    let rule = rule_selection().unwrap_or_else(AntinodeRule::twice_distance);
 
-    println!("{}", locations.len());
+    report(&array, &rule);
 }
 
 fn part_2(input: &str) {
     let array = Array::from(input);
 
-    let locations = array.find_unique_antinode_locations(array::AntinodeRule::Inline);
+    let rule = rule_selection().unwrap_or_else(AntinodeRule::inline);
 
-    println!("{}", locations.len());
-}
\ No newline at end of file
+    report(&array, &rule);
+}
+
+fn report(array: &Array, rule: &AntinodeRule) {
+    match output_selection() {
+        Output::Count => {
+            let locations = array.find_unique_antinode_locations(rule);
+
+            println!("{}", locations.len());
+        },
+        Output::Render => {
+            let antinodes = array.find_attributed_antinodes(rule);
+
+            println!("{}", array.render(&antinodes));
+        },
+        Output::Audit => {
+            let antinodes = array.find_attributed_antinodes(rule);
+            let coverage = Array::coverage(&antinodes);
+
+            let mut locations = coverage.keys().copied().collect::<Vec<_>>();
+            locations.sort();
+
+            for location in locations {
+                let position = array.position(location);
+
+                let sources = antinodes
+                    .iter()
+                    .filter(|a| a.location == location)
+                    .map(|a| {
+                        let (first, second) = (array.position(a.antennas.0), array.position(a.antennas.1));
+                        format!("{}({},{})-{}({},{})", a.frequencies.0, first.0, first.1, a.frequencies.1, second.0, second.1)
+                    })
+                    .collect::<Vec<_>>();
+
+                let occupied = array.antenna_at(location).map(|a| format!(" under antenna {a}")).unwrap_or_default();
+
+                println!("({},{}) x{}{occupied}: {}", position.0, position.1, coverage[&location], sources.join(" "));
+            }
+
+            println!("{}", coverage.len());
+        },
+    }
+}
+
+fn rule_selection() -> Option<AntinodeRule> {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    args.iter()
+        .position(|a| a == "--rule")
+        .and_then(|i| args.get(i + 1))
+        .map(|spec| AntinodeRule::from(spec.as_str()))
+}
+
+enum Output {
+    Count,
+    Render,
+    Audit,
+}
+
+fn output_selection() -> Output {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    for arg in args.iter().skip(2) {
+        match arg.as_str() {
+            "--count" => return Output::Count,
+            "--render" => return Output::Render,
+            "--audit" => return Output::Audit,
+            _ => {}
+        }
+    }
+
+    Output::Count
+}
@@ -1,206 +1,219 @@
-use std::collections::HashSet;
+use std::{cmp::Reverse, collections::{BinaryHeap, HashMap}};
 
-type Block = Option<u64>;
+use utils::Random;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Placement {
+    First,
+    Best,
+    Worst,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fragmentation {
+    pub free_extents: usize,
+    pub free_blocks: usize,
+    pub largest_free_extent: usize,
+    pub split_files: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileSpan {
+    pub id: u64,
+    pub start: usize,
+    pub length: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FreeSpan {
+    pub start: usize,
+    pub length: usize,
+}
 
 pub struct Disk {
-    blocks: Vec<Block>,
+    files: Vec<FileSpan>,
+    free: Vec<FreeSpan>,
+    size: usize,
 }
 
 impl Disk {
-    pub fn compact_blocks(self) -> Self {
-        let mut blocks = self.blocks;
-
-        let next_empty = |b: &[Block]| {
-            let mut empty_it = b.iter().enumerate();
-            loop {
-                if let Some((index, block)) = empty_it.next() {
-                    if block.is_none() {
-                        return Some(index);
-                    }
-                } else {
-                    break;
-                }
-            }
+    fn from_files(mut files: Vec<FileSpan>, size: usize) -> Self {
+        files.sort_by_key(|f| f.start);
 
-            None
-        };
+        let mut free = vec![];
+        let mut position = 0;
 
-        let next_file = |b: &[Block]| {
-            let mut file_it = b.iter().enumerate().rev();
-            loop {
-                if let Some((index, block)) = file_it.next() {
-                    if block.is_some() {
-                        return Some(index);
-                    }
-                } else {
-                    break;
-                }
+        for file in files.iter() {
+            if file.start > position {
+                free.push(FreeSpan { start: position, length: file.start - position });
             }
+            position = file.start + file.length;
+        }
 
-            None
-        };
+        if size > position {
+            free.push(FreeSpan { start: position, length: size - position });
+        }
 
-        loop {
-            let empty_block = next_empty(&blocks);
-            let file_block = next_file(&blocks);
+        Self { files, free, size }
+    }
 
-            if empty_block.is_none() || file_block.is_none() {
-                break;
-            }
+    pub fn compact_blocks(self) -> Self {
+        let mut files = self.files;
 
-            let empty_block = empty_block.unwrap();
-            let file_block = file_block.unwrap();
+        let mut moved = vec![];
 
-            if empty_block > file_block {
-                break;
+        // files still waiting to be moved are files[..remaining], the last one may have lost its tail already
+        let mut remaining = files.len();
+
+        for free in self.free.iter() {
+            let mut cursor = free.start;
+            let end = free.start + free.length;
+
+            while cursor < end && remaining > 0 && files[remaining - 1].start > cursor {
+                let file = &mut files[remaining - 1];
+
+                let take = file.length.min(end - cursor);
+
+                moved.push(FileSpan { id: file.id, start: cursor, length: take });
+
+                file.length -= take;
+                cursor += take;
+
+                if file.length == 0 {
+                    remaining -= 1;
+                }
             }
 
-            blocks.swap(empty_block, file_block);
+            if remaining == 0 || files[remaining - 1].start < free.start {
+                break;
+            }
         }
 
-        Self { blocks }
+        files.truncate(remaining);
+        files.retain(|f| f.length > 0);
+        files.extend(moved);
+
+        Self::from_files(files, self.size)
     }
 
-    pub fn compact_files(self) -> Self {
-        let mut blocks = self.blocks;
-
-        let mut moved_files = HashSet::new();
-
-        let mut next_file = |b: &[Block]| {
-            let mut file_it = b.iter().enumerate().rev();
-
-            let mut file = None;
-            let mut file_end = None;
-            let mut file_start = None;
-
-            loop {
-                if let Some((index, block)) = file_it.next() {
-                    if file_end.is_none() {
-                        if block.is_some() {
-                            let block_file = block.unwrap();
-                            if moved_files.contains(&block_file) {
-                                continue;
-                            }
-                            file = Some(block_file);
-                            file_end = Some(index);
-                        }
-                    } else if file_start.is_none() {
-                        if let Some(block) = block {
-                            if file.as_ref().unwrap() != block {
-                                // hit a different file block
-                                file_start = Some(index + 1);
-                                break;
-                            }
-                        } else {
-                            // hit empty blocks
-                            file_start = Some(index + 1);
-                            break;
-                        }
-                    } else {
-                        panic!("still searching for file locations after start and end have been found")
-                    }
-                } else {
-                    break;
-                }
-            }
+    pub fn compact_files(self, placement: Placement) -> Self {
+        let mut files = self.files;
+
+        // one min-heap of free span starts for every free span length
+        let max_length = self.free.iter().map(|f| f.length).max().unwrap_or(0);
 
-            if file_start.is_some() && file_end.is_some() {
-                moved_files.insert(file.unwrap());
-                Some((file_start.unwrap(), file_end.unwrap()))
-            } else {
-                None
+        let mut free_by_length = vec![BinaryHeap::new(); max_length + 1];
+
+        for free in self.free.iter() {
+            free_by_length[free.length].push(Reverse(free.start));
+        }
+
+        for file in files.iter_mut().rev() {
+            if file.length == 0 {
+                continue;
             }
-        };
 
-        let next_empty = |b: &[Block], required_space| {
-            let mut empty_it = b.iter().enumerate();
-
-            loop {
-                let mut empty_start = None;
-                let mut empty_end = None;
-
-                loop {
-                    if let Some((index, block)) = empty_it.next() {
-                        if empty_start.is_none() {
-                            if block.is_none() {
-                                empty_start = Some(index);
-                            }
-                        }
-                        else if empty_end.is_none() {
-                            if block.is_some() {
-                                empty_end = Some(index - 1);
-                                break;
-                            }
-                        } else {
-
-                        }
-                    } else {
-                        break;
-                    }
+            // only the leftmost span of each length can matter - if it is past the file so are the rest
+            let candidates = free_by_length
+                .iter()
+                .enumerate()
+                .skip(file.length)
+                .filter_map(|(length, heap)| heap.peek().map(|&Reverse(start)| (start, length)))
+                .filter(|&(start, _)| start < file.start);
+
+            let best = match placement {
+                Placement::First => candidates.min_by_key(|&(start, _)| start),
+                Placement::Best => candidates.min_by_key(|&(_, length)| length),
+                Placement::Worst => candidates.max_by_key(|&(_, length)| length),
+            };
+
+            if let Some((start, length)) = best {
+                free_by_length[length].pop();
+
+                // space freed behind the file is never used - only files further left are still to move
+                if length > file.length {
+                    free_by_length[length - file.length].push(Reverse(start + file.length));
                 }
 
-                if empty_end.is_some() && empty_end.is_some() {
-                    let empty_start = empty_start.unwrap();
-                    let empty_end = empty_end.unwrap();
-    
-                    let size = empty_end - empty_start + 1;
-                    if size >= required_space {
-                        break Some((empty_start, empty_end));
-                    }
-                } else {
-                    break None;
-                }
+                file.start = start;
             }
-        };
+        }
 
-        loop {
-            let file_block = next_file(&blocks);
-            
-            if file_block.is_none() {
-                break;
+        Self::from_files(files, self.size)
+    }
+
+    // file ids are implied by position in the dense format, so ids are renumbered left to right and spans
+    // longer than nine blocks are broken up with empty files or empty free space
+    pub fn to_dense_map(&self) -> String {
+        let mut map = String::new();
+
+        let push_run = |map: &mut String, mut length: usize| {
+            while length > 9 {
+                map.push_str("90");
+                length -= 9;
             }
+            map.push(char::from_digit(length as u32, 10).unwrap());
+        };
 
-            let (file_start, file_end) = file_block.unwrap();
+        let mut position = 0;
 
-            let file_size = file_end - file_start + 1;
+        for file in self.files.iter() {
+            if map.is_empty() && file.start > 0 {
+                map.push('0');
+            }
 
-            loop {
-                let empty_block = next_empty(&blocks, file_size);
+            if !map.is_empty() {
+                push_run(&mut map, file.start - position);
+            }
 
-                if empty_block.is_none() {
-                    break;
-                }
+            push_run(&mut map, file.length);
 
-                let (empty_start, empty_end) = empty_block.unwrap();
+            position = file.start + file.length;
+        }
 
-                if empty_end > file_start {
-                    break;
-                }
+        if self.size > position {
+            if map.is_empty() {
+                map.push('0');
+            }
 
-                let empty_size = empty_end - empty_start + 1;
+            push_run(&mut map, self.size - position);
+        }
 
-                if empty_size < file_size {
-                    continue;
-                }
+        map
+    }
 
-                for offset in 0..file_size {
-                    blocks.swap(empty_start + offset, file_start + offset);
-                }
+    pub fn fragmentation(&self) -> Fragmentation {
+        let mut extents: HashMap<u64, usize> = HashMap::new();
+        let mut previous: Option<&FileSpan> = None;
 
-                break;
+        for file in self.files.iter().filter(|f| f.length > 0) {
+            let continues = previous.is_some_and(|p| p.id == file.id && p.start + p.length == file.start);
+
+            if !continues {
+                *extents.entry(file.id).or_default() += 1;
             }
+
+            previous = Some(file);
         }
 
-        Self { blocks }
+        Fragmentation {
+            free_extents: self.free.len(),
+            free_blocks: self.free.iter().map(|f| f.length).sum(),
+            largest_free_extent: self.free.iter().map(|f| f.length).max().unwrap_or(0),
+            split_files: extents.values().filter(|&&e| e > 1).count(),
+        }
     }
 
     pub fn checksum(&self) -> u64 {
-        self.blocks.iter()
-            .enumerate()
-            .filter(|(_, b)| b.is_some())
-            .map(|(i, b)| {
-                let b = b.unwrap();
-                u64::try_from(i).unwrap() * b
+        self.files.iter()
+            .map(|f| {
+                let start = f.start as u64;
+                let length = f.length as u64;
+
+                // sum of start..start + length without visiting each block
+                let index_sum = start * length + length * length.saturating_sub(1) / 2;
+
+                f.id * index_sum
             })
             .sum()
     }
@@ -208,40 +221,39 @@ impl Disk {
 
 impl From<&str> for Disk {
     fn from(value: &str) -> Self {
-        let mut processing_file = true;
-        let mut file_index = 0;
-
-        let mut disk_map_itr = value.trim().chars();
+        let mut files = vec![];
+        let mut free = vec![];
 
-        let mut blocks = vec![];
+        let mut position = 0;
 
-        loop {
-            if let Some(digit) = disk_map_itr.next() {
-                let count = digit.to_digit(10).unwrap();
-                
-                let block = if processing_file {
-                    let block = Some(file_index);
-                    file_index += 1;
-                    block
-                } else {
-                    None
-                };
+        for (index, digit) in value.trim().chars().enumerate() {
+            let length = digit.to_digit(10).unwrap() as usize;
 
-                processing_file = !processing_file;
-
-                for _ in 0..count {
-                    blocks.push(block);
-                }
-
-            } else {
-                break;
+            if index % 2 == 0 {
+                files.push(FileSpan { id: (index / 2) as u64, start: position, length });
+            } else if length > 0 {
+                free.push(FreeSpan { start: position, length });
             }
+
+            position += length;
         }
 
-        Self { blocks }
+        Self { files, free, size: position }
     }
 }
 
+pub fn generate_disk_map(length: usize, seed: u64) -> String {
+    let mut random = Random::new(seed);
+
+    (0..length)
+        .map(|index| {
+            // files are never empty, free space can be
+            let digit = if index % 2 == 0 { 1 + random.below(9) } else { random.below(10) };
+            char::from_digit(digit as u32, 10).unwrap()
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -275,7 +287,7 @@ const SIMPLE_EXAMPLE: &'static str = "2333133121414131402";
     fn basic_example_files() {
         let disk = Disk::from(BASIC_EXAMPLE);
 
-        let disk = disk.compact_files();
+        let disk = disk.compact_files(Placement::First);
 
         let checksum = disk.checksum();
 
@@ -286,10 +298,108 @@ const SIMPLE_EXAMPLE: &'static str = "2333133121414131402";
     fn simple_example_files() {
         let disk = Disk::from(SIMPLE_EXAMPLE);
 
-        let disk = disk.compact_files();
+        let disk = disk.compact_files(Placement::First);
 
         let checksum = disk.checksum();
 
         assert_eq!(2858, checksum);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn block_compaction_splits_files() {
+        let disk = Disk::from(BASIC_EXAMPLE).compact_blocks();
+
+        // 022111222......
+        assert_eq!(disk.files, vec![
+            FileSpan { id: 0, start: 0, length: 1 },
+            FileSpan { id: 2, start: 1, length: 2 },
+            FileSpan { id: 1, start: 3, length: 3 },
+            FileSpan { id: 2, start: 6, length: 3 },
+        ]);
+        assert_eq!(disk.free, vec![FreeSpan { start: 9, length: 6 }]);
+    }
+
+    #[test]
+    fn file_compaction_spans() {
+        let disk = Disk::from(SIMPLE_EXAMPLE).compact_files(Placement::First);
+
+        // 00992111777.44.333....5555.6666.....8888..
+        let starts = disk.files.iter().map(|f| (f.id, f.start)).collect::<Vec<_>>();
+
+        assert_eq!(starts, vec![(0, 0), (9, 2), (2, 4), (1, 5), (7, 8), (4, 12), (3, 15), (5, 22), (6, 27), (8, 36)]);
+        assert_eq!(disk.size, 42);
+    }
+
+    #[test]
+    fn generated_map() {
+        let map = generate_disk_map(20001, 7);
+
+        let blocks = Disk::from(map.as_str()).compact_blocks();
+        let files = Disk::from(map.as_str()).compact_files(Placement::First);
+
+        let used = blocks.files.iter().map(|f| f.length).sum::<usize>();
+
+        assert_eq!(blocks.free.len(), 1);
+        assert_eq!(blocks.free[0].start, used);
+        assert!(files.checksum() > 0);
+    }
+
+    #[test]
+    fn dense_map_round_trip() {
+        assert_eq!(Disk::from(SIMPLE_EXAMPLE).to_dense_map(), SIMPLE_EXAMPLE);
+        assert_eq!(Disk::from(BASIC_EXAMPLE).to_dense_map(), BASIC_EXAMPLE);
+
+        // 022111222...... - the split file 2 comes back as two files
+        let disk = Disk::from(BASIC_EXAMPLE).compact_blocks();
+
+        assert_eq!(disk.to_dense_map(), "10203036");
+
+        let blocks = Disk::from(disk.to_dense_map().as_str());
+
+        assert_eq!(blocks.free, disk.free);
+        assert_eq!(blocks.size, disk.size);
+
+        let long = Disk { files: vec![FileSpan { id: 0, start: 3, length: 12 }], free: vec![], size: 20 };
+
+        assert_eq!(long.to_dense_map(), "039035");
+    }
+
+    #[test]
+    fn fragmentation_stats() {
+        let disk = Disk::from(BASIC_EXAMPLE).compact_blocks();
+
+        assert_eq!(disk.fragmentation(), Fragmentation {
+            free_extents: 1,
+            free_blocks: 6,
+            largest_free_extent: 6,
+            split_files: 1,
+        });
+
+        let disk = Disk::from(SIMPLE_EXAMPLE).compact_files(Placement::First);
+
+        let fragmentation = disk.fragmentation();
+
+        assert_eq!(fragmentation.split_files, 0);
+        assert_eq!(fragmentation.free_blocks, 14);
+        assert_eq!(fragmentation.free_extents, 6);
+        assert_eq!(fragmentation.largest_free_extent, 5);
+    }
+
+    #[test]
+    fn placement_policies() {
+        // 0...1..2....33
+        let map = "1312142";
+
+        let starts = |placement| {
+            let disk = Disk::from(map).compact_files(placement);
+            disk.files.iter().map(|f| (f.id, f.start)).collect::<Vec<_>>()
+        };
+
+        // 0332.1........
+        assert_eq!(starts(Placement::First), vec![(0, 0), (3, 1), (2, 3), (1, 4)]);
+        // 021..33.......
+        assert_eq!(starts(Placement::Best), vec![(0, 0), (2, 1), (1, 2), (3, 5)]);
+        // 021.....33....
+        assert_eq!(starts(Placement::Worst), vec![(0, 0), (2, 1), (1, 2), (3, 8)]);
+    }
+}
@@ -1,4 +1,6 @@
-use disk_map::Disk;
+use std::time::Instant;
+
+use disk_map::{generate_disk_map, Disk, Placement};
 use utils::{part_selection, Part, Timer};
 
 mod disk_map;
@@ -6,6 +8,11 @@ mod disk_map;
 fn main() {
     let _timer = Timer::new();
 
+    if let Some(length) = benchmark_selection() {
+        benchmark(length);
+        return;
+    }
+
     let text = std::fs::read_to_string("./day-09/input.txt").unwrap();
 
     let part = part_selection();
@@ -20,16 +27,99 @@ fn part_1(input: &str) {
     let disk = Disk::from(input);
     let disk = disk.compact_blocks();
 
-    let checksum = disk.checksum();
-
-    println!("{checksum}");
+    report(&disk);
 }
 
 fn part_2(input: &str) {
+    if compare_selection() {
+        compare(input);
+        return;
+    }
+
     let disk = Disk::from(input);
-    let disk = disk.compact_files();
+    let disk = disk.compact_files(placement_selection());
+
+    report(&disk);
+}
+
+fn report(disk: &Disk) {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    if args.iter().any(|a| a == "--dense") {
+        println!("{}", disk.to_dense_map());
+    }
+
+    if args.iter().any(|a| a == "--stats") {
+        let fragmentation = disk.fragmentation();
+
+        println!(
+            "{} free blocks in {} extents, largest {}, {} split files",
+            fragmentation.free_blocks,
+            fragmentation.free_extents,
+            fragmentation.largest_free_extent,
+            fragmentation.split_files,
+        );
+    }
 
     let checksum = disk.checksum();
 
     println!("{checksum}");
 }
+
+fn compare(input: &str) {
+    for placement in [Placement::First, Placement::Best, Placement::Worst] {
+        let disk = Disk::from(input).compact_files(placement);
+
+        let fragmentation = disk.fragmentation();
+
+        println!(
+            "{placement:?} fit: checksum {}, {} free extents, largest {}",
+            disk.checksum(),
+            fragmentation.free_extents,
+            fragmentation.largest_free_extent,
+        );
+    }
+}
+
+fn benchmark(length: usize) {
+    let map = generate_disk_map(length, 2024);
+
+    let start = Instant::now();
+    let blocks = Disk::from(map.as_str()).compact_blocks().checksum();
+    let blocks_seconds = start.elapsed().as_secs_f64();
+
+    println!("Block compaction of {length} digits gave checksum {blocks} in {blocks_seconds} seconds");
+
+    let start = Instant::now();
+    let files = Disk::from(map.as_str()).compact_files(Placement::First).checksum();
+    let files_seconds = start.elapsed().as_secs_f64();
+
+    println!("File compaction of {length} digits gave checksum {files} in {files_seconds} seconds");
+}
+
+fn benchmark_selection() -> Option<usize> {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    let index = args.iter().position(|a| a == "--benchmark")?;
+
+    Some(args.get(index + 1).and_then(|l| l.parse().ok()).unwrap_or(1_000_000))
+}
+
+fn compare_selection() -> bool {
+    std::env::args().any(|a| a == "--compare")
+}
+
+fn placement_selection() -> Placement {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    for arg in args.iter().skip(2) {
+        match arg.as_str() {
+            "--first-fit" => return Placement::First,
+            "--best-fit" => return Placement::Best,
+            "--worst-fit" => return Placement::Worst,
+            _ => {}
+        }
+    }
+
+    Placement::First
+}
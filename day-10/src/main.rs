@@ -1,5 +1,5 @@
-use map::{rate_trails, score_trails, FindTrailRule, Map};
-use utils::{part_selection, Part, Timer};
+use map::{rate_trails, sample_trails, score_trails, FindTrailRule, Map, Path, TrailRule};
+use utils::{part_selection, Part, Position, Timer};
 
 mod map;
 
@@ -10,6 +10,11 @@ fn main() {
 
     let part = part_selection();
 
+    if let Some(export) = export_selection() {
+        export_trails(&text, export);
+        return;
+    }
+
     match part {
         Part::One => part_1(&text),
         Part::Two => part_2(&text),
@@ -18,20 +23,134 @@ fn main() {
 
 fn part_1(input: &str) {
     let map = Map::from(input);
+    let terrain = terrain_selection();
 
-    let trailhead_trails = map.find_trailhead_trails(FindTrailRule::Any);
+    let score = match method_selection() {
+        Method::Levels => map.summarise_trailheads(&terrain).iter().map(|s| s.score).sum(),
+        Method::Enumerate => {
+            let trailhead_trails = map.find_trailhead_trails(FindTrailRule::Any, &terrain);
 
-    let score = score_trails(&trailhead_trails);
+            score_trails(&trailhead_trails)
+        },
+    };
 
     println!("{score}");
 }
 
 fn part_2(input: &str) {
     let map = Map::from(input);
+    let terrain = terrain_selection();
 
-    let trailhead_trails = map.find_trailhead_trails(FindTrailRule::All);
+    let rating = match method_selection() {
+        Method::Levels => map.summarise_trailheads(&terrain).iter().map(|s| s.rating).sum(),
+        Method::Enumerate => {
+            let trailhead_trails = map.find_trailhead_trails(FindTrailRule::All, &terrain);
 
-    let rating = rate_trails(&trailhead_trails);
+            rate_trails(&trailhead_trails) as u128
+        },
+    };
 
     println!("{rating}");
-}
\ No newline at end of file
+}
+
+fn export_trails(input: &str, export: Export) {
+    let map = Map::from(input);
+    let terrain = terrain_selection();
+
+    let args = std::env::args().collect::<Vec<_>>();
+    let count_arg = |flag: &str| {
+        args.iter()
+            .position(|a| a == flag)
+            .and_then(|i| args.get(i + 1))
+            .map(|c| c.parse::<usize>().unwrap())
+    };
+
+    // --trailhead row,column restricts the export to the trails from one trailhead
+    let found = match args.iter().position(|a| a == "--trailhead").and_then(|i| args.get(i + 1)) {
+        Some(location) => {
+            let (row, column) = location.split_once(',').expect("Trailhead expects row,column");
+            let trailhead = map.index(&Position(row.parse().unwrap(), column.parse().unwrap()));
+
+            map.trailhead_trails(&terrain, trailhead)
+        },
+        None => map.trails(&terrain),
+    };
+
+    let trails: Box<dyn Iterator<Item = Path>> = match (count_arg("--sample"), count_arg("--limit")) {
+        (Some(count), _) => Box::new(sample_trails(found, count, 2024).into_iter()),
+        (None, Some(limit)) => Box::new(found.take(limit)),
+        (None, None) => Box::new(found),
+    };
+
+    match export {
+        Export::Json => {
+            println!("[");
+            for (number, trail) in trails.enumerate() {
+                let separator = if number > 0 { "," } else { "" };
+                println!("{separator}{}", map.trail_json(&trail));
+            }
+            println!("]");
+        },
+        Export::Csv => {
+            println!("trail,step,row,column,height");
+            for (number, trail) in trails.enumerate() {
+                println!("{}", map.trail_csv(number, &trail));
+            }
+        },
+        Export::Render => {
+            for trail in trails {
+                let trailhead = map.position(trail[0]);
+                println!("Trail from ({}, {})", trailhead.0, trailhead.1);
+                println!("{}\n", map.render(&[trail]));
+            }
+        },
+    }
+}
+
+enum Export {
+    Json,
+    Csv,
+    Render,
+}
+
+fn export_selection() -> Option<Export> {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    let index = args.iter().position(|a| a == "--export")?;
+
+    match args.get(index + 1).map(|f| f.as_str()) {
+        Some("json") => Some(Export::Json),
+        Some("csv") => Some(Export::Csv),
+        Some("render") => Some(Export::Render),
+        format => panic!("Unknown export format {format:?} - expected json, csv, or render"),
+    }
+}
+
+enum Method {
+    Levels,
+    Enumerate,
+}
+
+fn method_selection() -> Method {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    for arg in args.iter().skip(2) {
+        match arg.as_str() {
+            "--levels" => return Method::Levels,
+            "--enumerate" => return Method::Enumerate,
+            _ => {}
+        }
+    }
+
+    Method::Levels
+}
+
+fn terrain_selection() -> TrailRule {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    args.iter()
+        .position(|a| a == "--terrain")
+        .and_then(|i| args.get(i + 1))
+        .map(|spec| TrailRule::from(spec.as_str()))
+        .unwrap_or_default()
+}
@@ -1,14 +1,19 @@
-use std::collections::{BinaryHeap, HashMap};
+use std::{collections::{HashMap, HashSet, VecDeque}, ops::RangeInclusive};
 
-use utils::{Direction, Grid};
+use utils::{Direction, Grid, Position, Random};
 
 pub type Path = Vec<usize>;
 
 pub struct Map {
     flattened_topography: String,
     grid: Grid,
-    trailhead_locations: Vec<usize>,
-    destination_locations: Vec<usize>
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrailheadSummary {
+    pub trailhead: usize,
+    pub score: usize,
+    pub rating: u128,
 }
 
 #[derive(Clone, Copy)]
@@ -17,206 +22,430 @@ pub enum FindTrailRule {
     All
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrailRule {
+    pub height_change: RangeInclusive<i32>,
+    pub diagonal: bool,
+    pub impassable: Vec<char>,
+    pub start_heights: Vec<u32>,
+    pub end_heights: Vec<u32>,
+    pub strict: bool,
+}
+
+impl Default for TrailRule {
+    fn default() -> Self {
+        Self {
+            height_change: 1..=1,
+            diagonal: false,
+            impassable: vec![IMPASSABLE],
+            start_heights: vec![TRAILHEAD],
+            end_heights: vec![DESTINATION],
+            strict: false,
+        }
+    }
+}
+
+impl TrailRule {
+    fn height(&self, c: char) -> Option<u32> {
+        if let Some(height) = c.to_digit(10) {
+            Some(height)
+        } else if self.impassable.contains(&c) || !self.strict {
+            None
+        } else {
+            panic!("Unexpected terrain '{c}' - expected a height or one of {:?}", self.impassable)
+        }
+    }
+
+    fn directions(&self) -> &'static [Direction] {
+        if self.diagonal {
+            Direction::all()
+        } else {
+            Direction::orthogonal()
+        }
+    }
+
+    fn valid_move(&self, from: u32, to: u32) -> bool {
+        self.height_change.contains(&(to as i32 - from as i32))
+    }
+
+    // when every step climbs the terrain can be walked level by level without revisiting a cell
+    fn always_climbs(&self) -> bool {
+        *self.height_change.start() > 0
+    }
+}
+
+/*
+ * comma separated settings, any not given keep the puzzle defaults
+ * e.g. "change=-1..2,diagonal,impassable=.#,start=0,end=8 9"
+ * terrain that is not a height is impassable, unless strict rejects anything not listed as impassable
+ */
+impl From<&str> for TrailRule {
+    fn from(text: &str) -> Self {
+        let mut rule = Self::default();
+
+        let heights = |value: &str| {
+            value.split_whitespace().map(|h| h.parse::<u32>().unwrap()).collect::<Vec<_>>()
+        };
+
+        for setting in text.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            let (name, value) = setting.split_once('=').unwrap_or((setting, ""));
+
+            match name {
+                "change" => {
+                    let (low, high) = value.split_once("..").expect("Height change expects low..high");
+                    rule.height_change = low.parse().unwrap()..=high.parse().unwrap();
+                },
+                "diagonal" => rule.diagonal = true,
+                "impassable" => rule.impassable = value.chars().collect(),
+                "start" => rule.start_heights = heights(value),
+                "end" => rule.end_heights = heights(value),
+                "strict" => rule.strict = true,
+                _ => panic!("Unknown trail rule setting '{setting}' - expected change, diagonal, impassable, start, end, or strict"),
+            }
+        }
+
+        rule
+    }
+}
+
 impl Map {
-    pub fn find_trailhead_trails(&self, rule: FindTrailRule) -> HashMap<usize, Vec<Path>> {
+    fn heights(&self, rule: &TrailRule) -> Vec<Option<u32>> {
+        self.flattened_topography.chars().map(|c| rule.height(c)).collect()
+    }
+
+    fn locations(heights: &[Option<u32>], wanted: &[u32]) -> Vec<usize> {
+        heights.iter()
+            .enumerate()
+            .filter(|(_, h)| h.is_some_and(|h| wanted.contains(&h)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn next_steps(&self, rule: &TrailRule, heights: &[Option<u32>], index: usize) -> Vec<usize> {
+        let Some(height) = heights[index] else {
+            return vec![];
+        };
+
+        let position = self.grid.get_position(index).unwrap();
+
+        rule.directions()
+            .iter()
+            .filter_map(|d| self.grid.get_index(&position.adjacent(*d)).ok())
+            .filter(|&adjacent| heights[adjacent].is_some_and(|h| rule.valid_move(height, h)))
+            .collect()
+    }
+
+    pub fn find_trailhead_trails(&self, rule: FindTrailRule, terrain: &TrailRule) -> HashMap<usize, Vec<Path>> {
+        let heights = self.heights(terrain);
+
         let mut trailhead_trails: HashMap<usize, Vec<Path>> = HashMap::new();
 
-        for trailhead in self.trailhead_locations.iter() {
-            for destination in self.destination_locations.iter() {
-                match rule {
-                    FindTrailRule::Any => {
-                        let trail = find_any_trail(&self.grid, &self.flattened_topography, *trailhead, *destination);
-
-                        if let Some(trail) = trail {
-                            let trails = trailhead_trails.entry(*trailhead).or_default();
-                            trails.push(trail);
-                        }
-                    },
-                    FindTrailRule::All => {
-                        let found_trails = find_trails(&self.grid, &self.flattened_topography, *trailhead, *destination);
-
-                        for trail in found_trails.into_iter() {
-                            let existing_trails = trailhead_trails.entry(*trailhead).or_default();
-                            existing_trails.push(trail);
-                        }
+        match rule {
+            FindTrailRule::Any => {
+                for trailhead in Self::locations(&heights, &terrain.start_heights) {
+                    let found_trails = self.find_any_trails(terrain, &heights, trailhead);
+
+                    if !found_trails.is_empty() {
+                        trailhead_trails.insert(trailhead, found_trails);
                     }
                 }
-
-            }
+            },
+            FindTrailRule::All => {
+                for trail in Trails::new(self, terrain, heights) {
+                    trailhead_trails.entry(trail[0]).or_default().push(trail);
+                }
+            },
         }
 
         trailhead_trails
     }
-}
 
-pub fn find_any_trail(grid: &Grid, flattened_topography: &str, start: usize, end: usize) -> Option<Path> {
-    let mut trails: BinaryHeap<Location> = BinaryHeap::new();
-    trails.push(Location::from(start));
+    pub fn trails<'a>(&'a self, terrain: &'a TrailRule) -> Trails<'a> {
+        Trails::new(self, terrain, self.heights(terrain))
+    }
 
-    let mut came_from: HashMap<usize, usize> = HashMap::new();
+    pub fn trailhead_trails<'a>(&'a self, terrain: &'a TrailRule, trailhead: usize) -> Trails<'a> {
+        let mut trails = self.trails(terrain);
+        trails.trailheads = vec![trailhead].into_iter();
+        trails
+    }
 
-    let mut found_end = false;
+    pub fn index(&self, position: &Position) -> usize {
+        self.grid.get_index(position).unwrap()
+    }
 
-    while !trails.is_empty() {
-        let current = trails.pop().unwrap();
+    pub fn position(&self, index: usize) -> Position {
+        self.grid.get_position(index).unwrap()
+    }
 
-        if current.index == end {
-            found_end = true;
-            break;
-        }
+    pub fn trail_json(&self, trail: &Path) -> String {
+        let cells = trail.iter()
+            .map(|&index| {
+                let position = self.position(index);
+                format!("[{},{}]", position.0, position.1)
+            })
+            .collect::<Vec<_>>();
+
+        let trailhead = self.position(trail[0]);
+
+        format!("{{\"trailhead\":[{},{}],\"cells\":[{}]}}", trailhead.0, trailhead.1, cells.join(","))
+    }
+
+    // one row per step - trail,step,row,column,height
+    pub fn trail_csv(&self, number: usize, trail: &Path) -> String {
+        trail.iter()
+            .enumerate()
+            .map(|(step, &index)| {
+                let position = self.position(index);
+                let height = self.flattened_topography.as_bytes()[index] as char;
+                format!("{number},{step},{},{},{height}", position.0, position.1)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // the topography with everything off the given trails blanked out, like the puzzle examples
+    pub fn render(&self, trails: &[Path]) -> String {
+        let on_trail = trails.iter().flatten().collect::<HashSet<_>>();
+
+        self.flattened_topography
+            .chars()
+            .enumerate()
+            .map(|(index, c)| if on_trail.contains(&index) { c } else { IMPASSABLE })
+            .collect::<Vec<_>>()
+            .chunks(self.grid.column_count as usize)
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 
-        let current_position = grid.get_position(current.index).unwrap();
-        let current_value = flattened_topography.chars().nth(current.index).unwrap();
+    // one shortest trail to each reachable destination
+    fn find_any_trails(&self, terrain: &TrailRule, heights: &[Option<u32>], start: usize) -> Vec<Path> {
+        let mut came_from: HashMap<usize, usize> = HashMap::new();
+        let mut visited = HashSet::from([start]);
 
-        for direction in Direction::orthogonal() {
-            let adjacent_position = current_position.adjacent(*direction);
-            if !grid.validate_position(&adjacent_position, false) {
+        let mut trails = VecDeque::from([start]);
+
+        let mut ends = vec![];
+
+        while let Some(current) = trails.pop_front() {
+            if current != start && heights[current].is_some_and(|h| terrain.end_heights.contains(&h)) {
+                ends.push(current);
                 continue;
             }
-            let adjacent = grid.get_index(&adjacent_position).unwrap();
-            let adjacent_value = flattened_topography.chars().nth(adjacent).unwrap();
 
-            if valid_move(current_value, adjacent_value) {
-                trails.push(Location::from(adjacent));
-                came_from.insert(adjacent, current.index);
+            for adjacent in self.next_steps(terrain, heights, current) {
+                if visited.insert(adjacent) {
+                    came_from.insert(adjacent, current);
+                    trails.push_back(adjacent);
+                }
             }
         }
+
+        ends.sort();
+
+        ends.into_iter()
+            .map(|end| {
+                let mut path = vec![end];
+                let mut current = end;
+
+                while current != start {
+                    current = came_from[&current];
+                    path.push(current);
+                }
+
+                path.reverse();
+                path
+            })
+            .collect()
     }
 
-    if !found_end {
-        None
-    } else {
-        let mut path = vec![end];
-        let mut current = end;
+    /*
+     * walk the heights from the destinations down to the trailheads, carrying for each cell the set of destinations
+     * it can reach (score) and the number of distinct trails to them (rating) - only the levels a step can reach
+     * are held at a time
+     */
+    pub fn summarise_trailheads(&self, terrain: &TrailRule) -> Vec<TrailheadSummary> {
+        let heights = self.heights(terrain);
+
+        if !terrain.always_climbs() {
+            // trails can wander back down, so fall back to following each one
+            return Self::locations(&heights, &terrain.start_heights)
+                .into_iter()
+                .map(|trailhead| {
+                    let mut destinations = HashSet::new();
+                    let mut rating: u128 = 0;
+
+                    for trail in self.trailhead_trails(terrain, trailhead) {
+                        destinations.insert(*trail.last().unwrap());
+                        rating += 1;
+                    }
 
-        while current != start {
-            let prev = came_from[&current];
-            path.insert(0, prev);
-            current = prev;
+                    TrailheadSummary { trailhead, score: destinations.len(), rating }
+                })
+                .collect();
         }
 
-        Some(path)
-    }
-}
+        let mut levels: Vec<Vec<usize>> = vec![vec![]; 10];
 
-struct Location {
-    index: usize
-}
+        for (index, height) in heights.iter().enumerate() {
+            if let Some(height) = height {
+                levels[*height as usize].push(index);
+            }
+        }
+
+        let reach = *terrain.height_change.end() as usize;
+
+        let mut known: HashMap<usize, (HashSet<usize>, u128)> = HashMap::new();
+
+        for height in (0..levels.len()).rev() {
+            for &index in levels[height].iter() {
+                if terrain.end_heights.contains(&(height as u32)) {
+                    known.insert(index, (HashSet::from([index]), 1));
+                    continue;
+                }
+
+                let mut destinations = HashSet::new();
+                let mut trail_count: u128 = 0;
+
+                for adjacent in self.next_steps(terrain, &heights, index) {
+                    if let Some((adjacent_destinations, adjacent_count)) = known.get(&adjacent) {
+                        destinations.extend(adjacent_destinations.iter().copied());
+                        trail_count = trail_count.checked_add(*adjacent_count).expect("Trail count overflowed u128");
+                    }
+                }
+
+                if trail_count > 0 {
+                    known.insert(index, (destinations, trail_count));
+                }
+            }
 
-impl From<usize> for Location {
-    fn from(value: usize) -> Self {
-        Self { index: value }
+            // nothing below this level can step up past the reach
+            if let Some(expired) = levels.get(height + reach) {
+                for index in expired.iter() {
+                    known.remove(index);
+                }
+            }
+        }
+
+        Self::locations(&heights, &terrain.start_heights)
+            .into_iter()
+            .map(|trailhead| {
+                let (score, rating) = known.get(&trailhead).map(|(d, c)| (d.len(), *c)).unwrap_or((0, 0));
+
+                TrailheadSummary { trailhead, score, rating }
+            })
+            .collect()
     }
 }
 
-impl PartialEq for Location {
-    fn eq(&self, _other: &Self) -> bool {
-        true
-    }
+/*
+ * every trail that reaches a destination without visiting a cell twice, stopping at the first destination
+ * trails are found depth first one at a time, so only the current trail and its untried steps are held
+ */
+pub struct Trails<'a> {
+    map: &'a Map,
+    terrain: &'a TrailRule,
+    heights: Vec<Option<u32>>,
+    trailheads: std::vec::IntoIter<usize>,
+    path: Path,
+    pending: Vec<Vec<usize>>,
 }
 
-impl Eq for Location {}
+impl<'a> Trails<'a> {
+    fn new(map: &'a Map, terrain: &'a TrailRule, heights: Vec<Option<u32>>) -> Self {
+        let trailheads = Map::locations(&heights, &terrain.start_heights).into_iter();
 
-impl PartialOrd for Location {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+        Self { map, terrain, heights, trailheads, path: vec![], pending: vec![] }
     }
-}
 
-impl Ord for Location {
-    fn cmp(&self, _other: &Self) -> std::cmp::Ordering {
-        std::cmp::Ordering::Equal
+    fn steps_from(&self, index: usize) -> Vec<usize> {
+        let mut steps = self.map.next_steps(self.terrain, &self.heights, index);
+        // popped from the back, so reverse to try directions in order
+        steps.reverse();
+        steps
     }
 }
 
+impl Iterator for Trails<'_> {
+    type Item = Path;
 
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.path.is_empty() {
+                let trailhead = self.trailheads.next()?;
 
-pub fn find_trails(grid: &Grid, flattened_topography: &str, start: usize, end: usize) -> Vec<Path> {
-    let mut final_paths = vec![];
+                self.path.push(trailhead);
+                self.pending.push(self.steps_from(trailhead));
+            }
 
-    let mut working_paths = vec![];
+            let Some(adjacent) = self.pending.last_mut().unwrap().pop() else {
+                self.path.pop();
+                self.pending.pop();
+                continue;
+            };
 
-    let path = vec![start];
-    working_paths.push(path);
+            if self.path.contains(&adjacent) {
+                continue;
+            }
 
-    while !working_paths.is_empty() {
-        let path = working_paths.remove(0);
+            self.path.push(adjacent);
 
-        let last = *path.last().unwrap();
+            if self.heights[adjacent].is_some_and(|h| self.terrain.end_heights.contains(&h)) {
+                let trail = self.path.clone();
+                self.path.pop();
+                return Some(trail);
+            }
 
-        if last == end {
-            final_paths.push(path);
-            continue;
+            self.pending.push(self.steps_from(adjacent));
         }
+    }
+}
 
-        let last_position = grid.get_position(last).unwrap();
-        let last_value = flattened_topography.chars().nth(last).unwrap();
-
-        for direction in Direction::orthogonal() {
-            let adjacent_position = last_position.adjacent(*direction);
-            if !grid.validate_position(&adjacent_position, false) {
-                continue;
-            }
-            let adjacent = grid.get_index(&adjacent_position).unwrap();
-            let adjacent_value = flattened_topography.chars().nth(adjacent).unwrap();
+// reservoir sample of trails, so only the sample is ever held
+pub fn sample_trails(trails: impl Iterator<Item = Path>, count: usize, seed: u64) -> Vec<Path> {
+    let mut random = Random::new(seed);
 
-            if valid_move(last_value, adjacent_value) {
-                if path.contains(&adjacent) {
-                    continue;
-                }
+    let mut sample = Vec::with_capacity(count);
 
-                let mut adjacent_path = path.clone();
-                adjacent_path.push(adjacent);
+    for (seen, trail) in trails.enumerate() {
+        if seen < count {
+            sample.push(trail);
+        } else {
+            let slot = random.below(seen as u64 + 1) as usize;
 
-                working_paths.push(adjacent_path);
+            if slot < count {
+                sample[slot] = trail;
             }
         }
     }
 
-    final_paths
-}
-
-fn valid_move(from: char, to: char) -> bool {
-    if to.is_digit(10) {
-        let from = from.to_digit(10).unwrap();
-        let to = to.to_digit(10).unwrap();
-
-        to > from && to - from == 1
-    } else {
-        false
-    }
+    sample
 }
 
+// number of distinct destinations reachable from each trailhead
 pub fn score_trails(trails: &HashMap<usize, Vec<Path>>) -> usize {
     trails.values()
-        .map(|p| p.len())
+        .map(|paths| paths.iter().filter_map(|p| p.last()).collect::<HashSet<_>>().len())
         .sum()
 }
 
+// number of distinct trails from each trailhead
 pub fn rate_trails(trails: &HashMap<usize, Vec<Path>>) -> usize {
     trails.values()
         .map(|p| p.len())
         .sum()
 }
 
-const TRAILHEAD: char = '0';
-const DESTINATION: char = '9';
+const TRAILHEAD: u32 = 0;
+const DESTINATION: u32 = 9;
+const IMPASSABLE: char = '.';
 
 impl From<&str> for Map {
     fn from(input: &str) -> Self {
         let (flattened_topography, grid) = Grid::parse_input(input);
 
-        let trailhead_locations = flattened_topography.chars().enumerate()
-            .filter(|(_, c)| TRAILHEAD == *c)
-            .map(|(i, _)| i)
-            .collect();
-
-        let destination_locations = flattened_topography.chars().enumerate()
-            .filter(|(_, c)| DESTINATION == *c)
-            .map(|(i, _)| i)
-            .collect();
-
-        Self { flattened_topography, grid, trailhead_locations, destination_locations }
+        Self { flattened_topography, grid }
     }
 }
 
@@ -234,7 +463,7 @@ mod tests {
         ".trim();
         let map = Map::from(input);
 
-        let trailhead_trails = map.find_trailhead_trails(FindTrailRule::Any);
+        let trailhead_trails = map.find_trailhead_trails(FindTrailRule::Any, &TrailRule::default());
 
         assert_eq!(1, trailhead_trails.len());
 
@@ -246,9 +475,7 @@ mod tests {
         assert_eq!(1, score);
     }
 
-    #[test]
-    fn simple_example() {
-        let input = r"
+    const SIMPLE_EXAMPLE: &str = r"
 89010123
 78121874
 87430965
@@ -257,11 +484,13 @@ mod tests {
 32019012
 01329801
 10456732
-    ".trim();
+    ";
 
-        let map = Map::from(input);
+    #[test]
+    fn simple_example() {
+        let map = Map::from(SIMPLE_EXAMPLE);
 
-        let trailhead_trails = map.find_trailhead_trails(FindTrailRule::Any);
+        let trailhead_trails = map.find_trailhead_trails(FindTrailRule::Any, &TrailRule::default());
 
         let score = score_trails(&trailhead_trails);
 
@@ -282,7 +511,7 @@ mod tests {
 
         let map = Map::from(input);
 
-        let trailhead_trails = map.find_trailhead_trails(FindTrailRule::All);
+        let trailhead_trails = map.find_trailhead_trails(FindTrailRule::All, &TrailRule::default());
         assert_eq!(1, trailhead_trails.len());
 
         let trails = trailhead_trails.get(&5).unwrap();
@@ -304,7 +533,7 @@ mod tests {
 
         let map = Map::from(input);
 
-        let trailhead_trails = map.find_trailhead_trails(FindTrailRule::All);
+        let trailhead_trails = map.find_trailhead_trails(FindTrailRule::All, &TrailRule::default());
         assert_eq!(1, trailhead_trails.len());
 
         let trails = trailhead_trails.get(&3).unwrap();
@@ -327,10 +556,192 @@ mod tests {
 
         let map = Map::from(input);
 
-        let trailhead_trails = map.find_trailhead_trails(FindTrailRule::All);
+        let trailhead_trails = map.find_trailhead_trails(FindTrailRule::All, &TrailRule::default());
         
         let rating = rate_trails(&trailhead_trails);
 
         assert_eq!(81, rating);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn score_and_rating_differ() {
+        let input = r"
+..90..9
+...1.98
+...2..7
+6543456
+765.987
+876....
+987....
+        ".trim();
+
+        let map = Map::from(input);
+
+        let trailhead_trails = map.find_trailhead_trails(FindTrailRule::All, &TrailRule::default());
+
+        assert_eq!(4, score_trails(&trailhead_trails));
+        assert_eq!(13, rate_trails(&trailhead_trails));
+    }
+
+    #[test]
+    fn summaries_match_enumeration() {
+        let map = Map::from(SIMPLE_EXAMPLE);
+
+        let summaries = map.summarise_trailheads(&TrailRule::default());
+
+        assert_eq!(9, summaries.len());
+        assert_eq!(36, summaries.iter().map(|s| s.score).sum::<usize>());
+        assert_eq!(81, summaries.iter().map(|s| s.rating).sum::<u128>());
+
+        let trailhead_trails = map.find_trailhead_trails(FindTrailRule::All, &TrailRule::default());
+
+        for summary in summaries {
+            let trails = &trailhead_trails[&summary.trailhead];
+            let destinations = trails.iter().filter_map(|p| p.last()).collect::<HashSet<_>>();
+
+            assert_eq!(summary.score, destinations.len());
+            assert_eq!(summary.rating, trails.len() as u128);
+        }
+    }
+
+    #[test]
+    fn summaries_of_large_map() {
+        // repeating 0..9 ramps in both directions, every cell has two ways up
+        let size = 300;
+
+        let input = (0..size)
+            .map(|row| (0..size).map(|column| char::from_digit(((row + column) % 10) as u32, 10).unwrap()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let map = Map::from(input.as_str());
+
+        let summaries = map.summarise_trailheads(&TrailRule::default());
+
+        // 9 steps each going right or down from a trailhead well inside the map
+        let inner = summaries.iter().find(|s| s.trailhead == 10 * size + 10).unwrap();
+
+        assert_eq!(512, inner.rating);
+        assert_eq!(10, inner.score);
+    }
+
+    #[test]
+    fn terrain_rules() {
+        let input = r"
+0#
+13
+24
+        ".trim();
+
+        let map = Map::from(input);
+
+        let rule = TrailRule::from("impassable=.#, end=4");
+        assert_eq!(map.summarise_trailheads(&rule), vec![TrailheadSummary { trailhead: 0, score: 0, rating: 0 }]);
+
+        // 0 1 3 4 and 0 1 2 4 both need a climb of two
+        let rule = TrailRule::from("impassable=.#, end=4, change=1..2");
+        assert_eq!(map.summarise_trailheads(&rule), vec![TrailheadSummary { trailhead: 0, score: 1, rating: 2 }]);
+
+        let rule = TrailRule::from("impassable=.#, end=4, change=-1..2");
+        let trails = map.find_trailhead_trails(FindTrailRule::All, &rule);
+        assert_eq!(rate_trails(&trails), 2);
+        assert_eq!(map.summarise_trailheads(&rule)[0].rating, 2);
+    }
+
+    #[test]
+    fn diagonal_trails() {
+        let input = r"
+0...
+.1..
+..2.
+...3
+        ".trim();
+
+        let map = Map::from(input);
+
+        assert!(map.summarise_trailheads(&TrailRule::from("end=3"))[0].rating == 0);
+
+        let rule = TrailRule::from("end=3, diagonal");
+        let trails = map.find_trailhead_trails(FindTrailRule::Any, &rule);
+
+        assert_eq!(trails[&0], vec![vec![0, 5, 10, 15]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected terrain '#'")]
+    fn unknown_terrain() {
+        Map::from("0#\n12").summarise_trailheads(&TrailRule::from("strict"));
+    }
+
+    #[test]
+    fn unknown_terrain_impassable() {
+        let map = Map::from("01#\n?23");
+
+        let summaries = map.summarise_trailheads(&TrailRule::from("end=3"));
+
+        assert_eq!(summaries, vec![TrailheadSummary { trailhead: 0, score: 1, rating: 1 }]);
+    }
+
+    #[test]
+    fn lazy_trails() {
+        let map = Map::from(SIMPLE_EXAMPLE);
+        let terrain = TrailRule::default();
+
+        assert_eq!(81, map.trails(&terrain).count());
+
+        let trailhead = map.grid.get_index(&Position(0, 2)).unwrap();
+
+        let trails = map.trailhead_trails(&terrain, trailhead).collect::<Vec<_>>();
+
+        assert_eq!(20, trails.len());
+        assert!(trails.iter().all(|t| t[0] == trailhead && t.len() == 10));
+        assert_eq!(trails.iter().collect::<HashSet<_>>().len(), 20);
+
+        assert_eq!(3, map.trails(&terrain).take(3).count());
+
+        let sample = sample_trails(map.trails(&terrain), 5, 42);
+
+        assert_eq!(5, sample.len());
+        assert_eq!(sample, sample_trails(map.trails(&terrain), 5, 42));
+    }
+
+    #[test]
+    fn export_trail() {
+        let input = r"
+.....0.
+..4321.
+..5..2.
+..6543.
+..7..4.
+..8765.
+..9....
+        ".trim();
+
+        let map = Map::from(input);
+        let terrain = TrailRule::default();
+
+        let trail = map.trails(&terrain).next().unwrap();
+
+        assert_eq!(
+            map.trail_json(&trail),
+            r#"{"trailhead":[0,5],"cells":[[0,5],[1,5],[2,5],[3,5],[4,5],[5,5],[5,4],[5,3],[5,2],[6,2]]}"#
+        );
+
+        let csv = map.trail_csv(1, &trail);
+
+        assert_eq!(csv.lines().next(), Some("1,0,0,5,0"));
+        assert_eq!(csv.lines().last(), Some("1,9,6,2,9"));
+
+        let expected = r"
+.....0.
+.....1.
+.....2.
+.....3.
+.....4.
+..8765.
+..9....
+        ".trim();
+
+        assert_eq!(map.render(&[trail]), expected);
+    }
+}
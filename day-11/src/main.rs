@@ -1,6 +1,8 @@
-use stone::{StoneCollection, StoneLine};
+use rules::{BlinkRules, StoneValue};
+use stone::{StoneCollection, StoneCounter, StoneLine};
 use utils::{part_selection, Part, Timer};
 
+mod rules;
 mod stone;
 
 fn main() {
@@ -18,17 +20,104 @@ fn main() {
 
 fn part_1(input: &str) {
     let stone_line = StoneLine::from(input);
+    let rules = rules_selection();
 
-    let count = StoneLine::multi_blink_stone_count(stone_line, 25);
+    let count = StoneLine::multi_blink_stone_count(stone_line, blinks_selection(25), &rules);
 
     println!("{count}");
 }
 
 fn part_2(input: &str) {
-    let stone_line = StoneLine::from(input);
-    let stone_collection = StoneCollection::from(stone_line);
+    if wide_selection() {
+        count_stones::<u128>(input);
+    } else {
+        count_stones::<u64>(input);
+    }
+}
 
-    let count = StoneCollection::multi_blink_stone_count(stone_collection, 75);
+fn count_stones<N: StoneValue>(input: &str) {
+    let rules = rules_selection();
+    let blinks = blinks_selection(75);
 
-    println!("{count}");
+    if let Some(queries) = query_selection() {
+        let mut counter = StoneCounter::<N>::new(&rules);
+
+        for (stone, depth) in queries {
+            match counter.count(N::from(stone), depth) {
+                Ok(count) => println!("{stone} after {depth}: {count}"),
+                Err(e) => println!("{stone} after {depth}: {e}"),
+            }
+        }
+
+        println!("memoised {} (stone, depth) pairs", counter.memo_size());
+
+        return;
+    }
+
+    let stone_collection = StoneCollection::<N>::from(StoneLine::from(input));
+
+    if stats_selection() {
+        match StoneCollection::multi_blink_stats(stone_collection, blinks, &rules) {
+            Ok(stats) => {
+                println!("blink,stones,distinct,largest");
+
+                for s in stats.iter() {
+                    println!("{},{},{},{}", s.blink, s.stones, s.distinct, s.largest);
+                }
+            },
+            Err(e) => println!("{e}"),
+        }
+
+        return;
+    }
+
+    match StoneCollection::multi_blink_stone_count(stone_collection, blinks, &rules) {
+        Ok(count) => println!("{count}"),
+        Err(e) => println!("{e}"),
+    }
+}
+
+fn rules_selection() -> BlinkRules {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    args.iter()
+        .position(|a| a == "--rules")
+        .and_then(|i| args.get(i + 1))
+        .map(|spec| BlinkRules::from(spec.as_str()))
+        .unwrap_or_default()
+}
+
+fn blinks_selection(default: u32) -> u32 {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    args.iter()
+        .position(|a| a == "--blinks")
+        .and_then(|i| args.get(i + 1))
+        .map(|n| n.parse().unwrap())
+        .unwrap_or(default)
+}
+
+fn wide_selection() -> bool {
+    std::env::args().any(|a| a == "--wide")
+}
+
+fn stats_selection() -> bool {
+    std::env::args().any(|a| a == "--stats")
+}
+
+// --query <stone>:<depth>,<stone>:<depth>...
+fn query_selection() -> Option<Vec<(u64, u32)>> {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    args.iter()
+        .position(|a| a == "--query")
+        .and_then(|i| args.get(i + 1))
+        .map(|queries| {
+            queries.split(',')
+                .map(|q| {
+                    let (stone, depth) = q.split_once(':').expect("Queries expect <stone>:<depth>");
+                    (stone.trim().parse().unwrap(), depth.trim().parse().unwrap())
+                })
+                .collect()
+        })
 }
\ No newline at end of file
@@ -0,0 +1,270 @@
+use std::{fmt::{Debug, Display}, hash::Hash, ops::{Div, Rem}};
+
+// stone numbers the rules can work on, u128 makes room for stones that would overflow a u64
+pub trait StoneValue: Copy + Eq + Hash + Ord + Debug + Display + From<u64> + Div<Output = Self> + Rem<Output = Self> {
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_pow(self, exponent: u32) -> Option<Self>;
+    fn ilog(self, base: Self) -> u32;
+    fn type_name() -> &'static str;
+}
+
+impl StoneValue for u64 {
+    fn checked_mul(self, other: Self) -> Option<Self> {
+        u64::checked_mul(self, other)
+    }
+
+    fn checked_add(self, other: Self) -> Option<Self> {
+        u64::checked_add(self, other)
+    }
+
+    fn checked_pow(self, exponent: u32) -> Option<Self> {
+        u64::checked_pow(self, exponent)
+    }
+
+    fn ilog(self, base: Self) -> u32 {
+        u64::ilog(self, base)
+    }
+
+    fn type_name() -> &'static str {
+        "u64"
+    }
+}
+
+impl StoneValue for u128 {
+    fn checked_mul(self, other: Self) -> Option<Self> {
+        u128::checked_mul(self, other)
+    }
+
+    fn checked_add(self, other: Self) -> Option<Self> {
+        u128::checked_add(self, other)
+    }
+
+    fn checked_pow(self, exponent: u32) -> Option<Self> {
+        u128::checked_pow(self, exponent)
+    }
+
+    fn ilog(self, base: Self) -> u32 {
+        u128::ilog(self, base)
+    }
+
+    fn type_name() -> &'static str {
+        "u128"
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Condition {
+    Always,
+    Equals(u64),
+    DigitCountMultipleOf(u32),
+    DigitCountAtLeast(u32),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Transform {
+    Replace(u64),
+    Multiply(u64),
+    Add(u64),
+    Split(u32),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlinkRule {
+    pub condition: Condition,
+    pub transform: Transform,
+}
+
+// the first rule whose condition holds decides what a stone becomes, digits are counted in the given base
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlinkRules {
+    pub rules: Vec<BlinkRule>,
+    pub base: u64,
+}
+
+impl Default for BlinkRules {
+    fn default() -> Self {
+        Self::from("0 -> =1; digits%2 -> split 2; * -> *2024")
+    }
+}
+
+impl BlinkRules {
+    pub fn digit_count<N: StoneValue>(&self, stone: N) -> u32 {
+        if stone == N::from(0) {
+            1
+        } else {
+            stone.ilog(N::from(self.base)) + 1
+        }
+    }
+
+    fn matches<N: StoneValue>(&self, condition: &Condition, stone: N) -> bool {
+        match *condition {
+            Condition::Always => true,
+            Condition::Equals(value) => stone == N::from(value),
+            Condition::DigitCountMultipleOf(k) => self.digit_count(stone).is_multiple_of(k),
+            Condition::DigitCountAtLeast(k) => self.digit_count(stone) >= k,
+        }
+    }
+
+    pub fn blink<N: StoneValue>(&self, stone: N) -> Result<Vec<N>, String> {
+        let rule = self.rules
+            .iter()
+            .find(|r| self.matches(&r.condition, stone))
+            .ok_or_else(|| format!("No blink rule matches stone {stone}"))?;
+
+        let stones = match rule.transform {
+            Transform::Replace(value) => vec![N::from(value)],
+            Transform::Multiply(factor) => {
+                let stone = stone.checked_mul(N::from(factor))
+                    .ok_or_else(|| format!("Stone {stone} × {factor} overflowed {}", N::type_name()))?;
+                vec![stone]
+            },
+            Transform::Add(value) => {
+                let stone = stone.checked_add(N::from(value))
+                    .ok_or_else(|| format!("Stone {stone} + {value} overflowed {}", N::type_name()))?;
+                vec![stone]
+            },
+            Transform::Split(parts) => self.split(stone, parts)?,
+        };
+
+        Ok(stones)
+    }
+
+    // split the digits into equal parts, most significant first
+    fn split<N: StoneValue>(&self, stone: N, parts: u32) -> Result<Vec<N>, String> {
+        let digit_count = self.digit_count(stone);
+
+        if !digit_count.is_multiple_of(parts) {
+            return Err(format!("Stone {stone} has {digit_count} digits which do not split into {parts} parts"));
+        }
+
+        // only fits when the stone splits into at least two parts, rules built by hand may ask for fewer
+        let factor = N::from(self.base)
+            .checked_pow(digit_count / parts)
+            .ok_or_else(|| format!("Stone {stone} split into {parts} parts overflowed {}", N::type_name()))?;
+
+        let mut stones = vec![N::from(0); parts as usize];
+        let mut remaining = stone;
+
+        for part in stones.iter_mut().rev() {
+            *part = remaining % factor;
+            remaining = remaining / factor;
+        }
+
+        Ok(stones)
+    }
+}
+
+/*
+ * rules separated by ';' as "<condition> -> <transform>", plus an optional "base=<n>"
+ * conditions: * (always), <n> (equals), digits%<k>, digits>=<k>
+ * transforms: =<n> (replace), *<n> (multiply), +<n> (add), split <k>
+ */
+impl From<&str> for BlinkRules {
+    fn from(text: &str) -> Self {
+        let mut rules = vec![];
+        let mut base = 10;
+
+        for rule in text.split(';').map(|r| r.trim()).filter(|r| !r.is_empty()) {
+            if let Some(value) = rule.strip_prefix("base=") {
+                base = value.parse().unwrap();
+                assert!(base >= 2, "Base must be at least 2");
+                continue;
+            }
+
+            let (condition, transform) = rule.split_once("->")
+                .unwrap_or_else(|| panic!("Blink rule '{rule}' expected <condition> -> <transform>"));
+
+            let (condition, transform) = (condition.trim(), transform.trim());
+
+            let condition = if condition == "*" {
+                Condition::Always
+            } else if let Some(k) = condition.strip_prefix("digits%") {
+                Condition::DigitCountMultipleOf(k.parse().unwrap())
+            } else if let Some(k) = condition.strip_prefix("digits>=") {
+                Condition::DigitCountAtLeast(k.parse().unwrap())
+            } else {
+                Condition::Equals(condition.parse().unwrap_or_else(|_| panic!("Unknown blink condition '{condition}'")))
+            };
+
+            let transform = if let Some(value) = transform.strip_prefix('=') {
+                Transform::Replace(value.parse().unwrap())
+            } else if let Some(factor) = transform.strip_prefix('*') {
+                Transform::Multiply(factor.parse().unwrap())
+            } else if let Some(value) = transform.strip_prefix('+') {
+                Transform::Add(value.parse().unwrap())
+            } else if let Some(parts) = transform.strip_prefix("split") {
+                let parts = parts.trim().parse().unwrap();
+                assert!(parts >= 2, "Split must be into at least 2 parts");
+                Transform::Split(parts)
+            } else {
+                panic!("Unknown blink transform '{transform}'")
+            };
+
+            rules.push(BlinkRule { condition, transform });
+        }
+
+        Self { rules, base }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn puzzle_rules() {
+        let rules = BlinkRules::default();
+
+        assert_eq!(Ok(vec![1]), rules.blink(0u64));
+        assert_eq!(Ok(vec![2024]), rules.blink(1u64));
+        assert_eq!(Ok(vec![10, 0]), rules.blink(1000u64));
+        assert_eq!(Ok(vec![2021976]), rules.blink(999u64));
+    }
+
+    #[test]
+    fn variant_rules() {
+        let rules = BlinkRules::from("digits%3 -> split 3; digits>=4 -> +1; * -> *7");
+
+        assert_eq!(Ok(vec![12, 34, 56]), rules.blink(123456u64));
+        assert_eq!(Ok(vec![1, 2, 3]), rules.blink(123u64));
+        assert_eq!(Ok(vec![1235]), rules.blink(1234u64));
+        assert_eq!(Ok(vec![84]), rules.blink(12u64));
+
+        // 0b1011 splits into 0b10 and 0b11
+        let rules = BlinkRules::from("base=2; digits%2 -> split 2; * -> *3");
+
+        assert_eq!(Ok(vec![2, 3]), rules.blink(11u64));
+        assert_eq!(Ok(vec![21]), rules.blink(7u64));
+    }
+
+    #[test]
+    fn checked_multiply() {
+        let rules = BlinkRules::default();
+
+        // 17 digits so it is multiplied rather than split
+        let stone = 10u64.pow(16);
+
+        assert_eq!(Err(format!("Stone {stone} × 2024 overflowed u64")), rules.blink(stone));
+        assert_eq!(Ok(vec![stone as u128 * 2024]), rules.blink(stone as u128));
+    }
+
+    #[test]
+    fn split_into_one_part() {
+        let rules = BlinkRules {
+            rules: vec![BlinkRule { condition: Condition::Always, transform: Transform::Split(1) }],
+            base: 10,
+        };
+
+        assert_eq!(Ok(vec![12]), rules.blink(12u64));
+
+        let stone = 10u64.pow(19);
+        assert_eq!(Err(format!("Stone {stone} split into 1 parts overflowed u64")), rules.blink(stone));
+    }
+
+    #[test]
+    #[should_panic(expected = "Split must be into at least 2 parts")]
+    fn parse_split_into_one_part() {
+        BlinkRules::from("* -> split 1").blink(10u64).unwrap();
+    }
+}
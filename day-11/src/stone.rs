@@ -1,76 +1,25 @@
 use std::collections::HashMap;
 
-pub trait Stone {
-    fn blink(self) -> BlinkResult;
-}
-
-impl Stone for u64 {
-    fn blink(self) -> BlinkResult {
-        if self == 0 {
-            BlinkResult::Replace(1)
-        } else if self.digit_count() % 2 == 0 {
-            let (a, b) = self.split_digits();
-
-            BlinkResult::Split(a, b)
-        } else {
-            BlinkResult::Replace(self * 2024)
-        }
-    }
-}
-
-pub enum BlinkResult {
-    Replace(u64),
-    Split(u64, u64),
-}
-
-trait StoneNumber {
-    fn digit_count(&self) -> u32;
-    fn split_digits(&self) -> (u64, u64);
-}
-
-impl StoneNumber for u64 {
-    fn digit_count(&self) -> u32 {
-        self.ilog10() + 1
-    }
-
-    fn split_digits(&self) -> (u64, u64) {
-        let digit_count = self.digit_count();
-
-        let factor = 10u64.pow(digit_count / 2);
-
-        let a = self / factor;
-        let b = self % factor;
-
-        (a, b)
-    }
-}
+use crate::rules::{BlinkRules, StoneValue};
 
 pub struct StoneLine {
     stones: Vec<u64>,
 }
 
 impl StoneLine {
-    pub fn blink(self) -> Self {
+    pub fn blink(self, rules: &BlinkRules) -> Self {
         let mut stones = vec![];
 
         for stone in self.stones.into_iter() {
-            match stone.blink() {
-                BlinkResult::Replace(s) => {
-                    stones.push(s);
-                },
-                BlinkResult::Split(a, b) => {
-                    stones.push(a);
-                    stones.push(b);
-                }
-            }
+            stones.extend(rules.blink(stone).unwrap_or_else(|e| panic!("{e}")));
         }
 
         Self { stones }
     }
 
-    pub fn multi_blink_stone_count(mut stone_line: StoneLine, count: u32) -> usize {
+    pub fn multi_blink_stone_count(mut stone_line: StoneLine, count: u32, rules: &BlinkRules) -> usize {
         for _ in 0..count {
-            stone_line = stone_line.blink();
+            stone_line = stone_line.blink(rules);
         }
 
         stone_line.stones.len()
@@ -87,61 +36,148 @@ impl From<&str> for StoneLine {
     }
 }
 
-pub struct StoneCollection {
-    stones: HashMap<u64, u64>,
+// stones grouped by number, u64 numbers for the puzzle or u128 when blinking long enough to overflow them
+pub struct StoneCollection<N: StoneValue> {
+    stones: HashMap<N, u128>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlinkStats<N: StoneValue> {
+    pub blink: u32,
+    pub stones: u128,
+    pub distinct: usize,
+    pub largest: N,
 }
 
-impl StoneCollection {
+fn add_count(total: u128, count: u128) -> Result<u128, String> {
+    total.checked_add(count).ok_or_else(|| "Stone count overflowed u128".to_string())
+}
+
+impl<N: StoneValue> StoneCollection<N> {
     pub fn from(stone_line: StoneLine) -> Self {
         let mut stones = HashMap::new();
 
         for s in stone_line.stones.into_iter() {
-            let count = stones.entry(s).or_default();
+            let count = stones.entry(N::from(s)).or_default();
             *count += 1;
         }
 
         Self { stones }
     }
 
-    pub fn blink(self) -> Self {
+    pub fn blink(self, rules: &BlinkRules) -> Result<Self, String> {
         let mut stones = HashMap::new();
 
         for (number, count) in self.stones.into_iter() {
-            match number.blink() {
-                BlinkResult::Replace(n) => {
-                    let total_count = stones.entry(n).or_default();
-                    *total_count += count
-                },
-                BlinkResult::Split(a, b) => {
-                    if a == b {
-                        let total_count = stones.entry(a).or_default();
-                        *total_count += count * 2;
-                    } else {
-                        let total_count_a = stones.entry(a).or_default();
-                        *total_count_a += count;
-
-                        let total_count_b = stones.entry(b).or_default();
-                        *total_count_b += count;
-                    }
-                },
+            for n in rules.blink(number)? {
+                let total_count = stones.entry(n).or_default();
+                *total_count = add_count(*total_count, count)?;
             }
         }
 
-        Self { stones }
+        Ok(Self { stones })
+    }
+
+    pub fn count(&self) -> Result<u128, String> {
+        self.stones.values().try_fold(0, |total, &count| add_count(total, count))
     }
 
-    pub fn multi_blink_stone_count(mut stone_collection: StoneCollection, count: u32) -> u64 {
+    pub fn stats(&self, blink: u32) -> Result<BlinkStats<N>, String> {
+        Ok(BlinkStats {
+            blink,
+            stones: self.count()?,
+            distinct: self.stones.len(),
+            largest: self.stones.keys().copied().max().unwrap_or(N::from(0)),
+        })
+    }
+
+    pub fn multi_blink_stone_count(mut stone_collection: Self, count: u32, rules: &BlinkRules) -> Result<u128, String> {
         for _ in 0..count {
-            stone_collection = stone_collection.blink();
+            stone_collection = stone_collection.blink(rules)?;
         }
 
-        let mut total_count = 0;
+        stone_collection.count()
+    }
 
-        for (_, count) in stone_collection.stones.iter() {
-            total_count += count;
+    // statistics before any blinks and after each one
+    pub fn multi_blink_stats(mut stone_collection: Self, count: u32, rules: &BlinkRules) -> Result<Vec<BlinkStats<N>>, String> {
+        let mut stats = vec![stone_collection.stats(0)?];
+
+        for blink in 1..=count {
+            stone_collection = stone_collection.blink(rules)?;
+            stats.push(stone_collection.stats(blink)?);
         }
 
-        total_count
+        Ok(stats)
+    }
+}
+
+/*
+ * how many stones a single stone becomes after some number of blinks, remembered for every (stone, depth)
+ * visited along the way so later queries sharing the same descendants are answered from the memo
+ * the walk keeps its own stack rather than recursing, so deep queries reach the overflow error instead of
+ * running out of thread stack
+ */
+pub struct StoneCounter<'a, N: StoneValue> {
+    rules: &'a BlinkRules,
+    memo: HashMap<(N, u32), u128>,
+}
+
+struct CountFrame<N: StoneValue> {
+    stone: N,
+    depth: u32,
+    children: Vec<N>,
+    next: usize,
+    total: u128,
+}
+
+impl<'a, N: StoneValue> StoneCounter<'a, N> {
+    pub fn new(rules: &'a BlinkRules) -> Self {
+        Self { rules, memo: HashMap::new() }
+    }
+
+    pub fn count(&mut self, stone: N, depth: u32) -> Result<u128, String> {
+        if depth == 0 {
+            return Ok(1);
+        }
+
+        if let Some(&count) = self.memo.get(&(stone, depth)) {
+            return Ok(count);
+        }
+
+        let mut stack = vec![CountFrame { stone, depth, children: self.rules.blink(stone)?, next: 0, total: 0 }];
+        let mut returned = None;
+
+        while let Some(frame) = stack.last_mut() {
+            if let Some(count) = returned.take() {
+                frame.total = add_count(frame.total, count)?;
+                frame.next += 1;
+            }
+
+            if frame.next == frame.children.len() {
+                self.memo.insert((frame.stone, frame.depth), frame.total);
+                returned = Some(frame.total);
+                stack.pop();
+                continue;
+            }
+
+            let (child, depth) = (frame.children[frame.next], frame.depth - 1);
+
+            if depth == 0 {
+                returned = Some(1);
+            } else if let Some(&count) = self.memo.get(&(child, depth)) {
+                returned = Some(count);
+            } else {
+                let children = self.rules.blink(child)?;
+                stack.push(CountFrame { stone: child, depth, children, next: 0, total: 0 });
+            }
+        }
+
+        Ok(returned.unwrap())
+    }
+
+    pub fn memo_size(&self) -> usize {
+        self.memo.len()
     }
 }
 
@@ -157,7 +193,7 @@ mod tests {
     fn basic_example() {
         let stone_line = StoneLine::from(BASIC_EXAMPLE);
 
-        let stone_line = stone_line.blink();
+        let stone_line = stone_line.blink(&BlinkRules::default());
 
         assert_eq!(vec![1, 2024, 1, 0, 9, 9, 2021976], stone_line.stones);
     }
@@ -167,15 +203,81 @@ mod tests {
         let mut stone_line = StoneLine::from(SIMPLE_EXAMPLE);
 
         for _ in 0..6 {
-            stone_line = stone_line.blink();
+            stone_line = stone_line.blink(&BlinkRules::default());
         }
 
         assert_eq!(22, stone_line.stones.len());
 
         for _ in 0..19 {
-            stone_line = stone_line.blink();
+            stone_line = stone_line.blink(&BlinkRules::default());
         }
 
         assert_eq!(55312, stone_line.stones.len());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn line_and_collection_agree() {
+        let rules = BlinkRules::from("0 -> =1; digits%3 -> split 3; digits%2 -> split 2; * -> *2024");
+
+        let line = StoneLine::multi_blink_stone_count(StoneLine::from(SIMPLE_EXAMPLE), 20, &rules);
+
+        let collection = StoneCollection::<u64>::from(StoneLine::from(SIMPLE_EXAMPLE));
+        let collection = StoneCollection::multi_blink_stone_count(collection, 20, &rules);
+
+        assert_eq!(Ok(line as u128), collection);
+    }
+
+    #[test]
+    fn wide_collection() {
+        let rules = BlinkRules::from("* -> *2024");
+
+        // 10^16 × 2024 no longer fits in a u64
+        let line = || StoneLine::from("10000000000000000");
+
+        let narrow = StoneCollection::<u64>::multi_blink_stone_count(StoneCollection::from(line()), 1, &rules);
+        assert_eq!(Err("Stone 10000000000000000 × 2024 overflowed u64".to_string()), narrow);
+
+        let wide = StoneCollection::<u128>::multi_blink_stone_count(StoneCollection::from(line()), 2, &rules);
+        assert_eq!(Ok(1), wide);
+    }
+
+    #[test]
+    fn memoised_count() {
+        let rules = BlinkRules::default();
+        let mut counter = StoneCounter::<u64>::new(&rules);
+
+        assert_eq!(Ok(1), counter.count(125, 0));
+        assert_eq!(Ok(55312), counter.count(125, 25).and_then(|a| Ok(a + counter.count(17, 25)?)));
+
+        // 253000 is what 125 becomes after one blink, so it is already remembered
+        let memo_size = counter.memo_size();
+        assert_eq!(Ok(19025), counter.count(125, 25));
+        assert_eq!(Ok(19025), counter.count(253000, 24));
+        assert_eq!(memo_size, counter.memo_size());
+    }
+
+    #[test]
+    fn deep_memoised_count() {
+        let rules = BlinkRules::default();
+        let mut counter = StoneCounter::<u128>::new(&rules);
+
+        assert_eq!(Err("Stone count overflowed u128".to_string()), counter.count(125, 100_000));
+
+        // one stone stays one stone, however deep
+        let rules = BlinkRules::from("* -> +1");
+        let mut counter = StoneCounter::<u64>::new(&rules);
+
+        assert_eq!(Ok(1), counter.count(0, 100_000));
+    }
+
+    #[test]
+    fn blink_stats() {
+        let collection = StoneCollection::<u64>::from(StoneLine::from(SIMPLE_EXAMPLE));
+
+        let stats = StoneCollection::multi_blink_stats(collection, 6, &BlinkRules::default()).unwrap();
+
+        assert_eq!(7, stats.len());
+        assert_eq!(BlinkStats { blink: 1, stones: 3, distinct: 3, largest: 253000 }, stats[1]);
+        assert_eq!(22, stats[6].stones);
+    }
+}
@@ -1,232 +1,229 @@
-use std::collections::{HashMap, HashSet};
+use utils::{Direction, Grid, Position};
 
-use utils::{Direction, Grid};
+use crate::{outline::{trace_outlines, Outline}, pricing::{Bill, BillLine, FencePricing, PerimeterPricing, SidesPricing}};
 
 pub struct Garden {
-    plants: Vec<char>,
     flattened_plants: String,
     grid: Grid,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub label: usize,
+    pub plant: char,
+    pub area: u32,
+    pub perimeter: u32,
+    pub garden_edge: u32,
+    pub sides: u32,
+    pub top_left: Position,
+    pub bottom_right: Position,
+}
+
+pub struct RegionLabels {
+    pub labels: Vec<usize>,
+    pub regions: Vec<Region>,
+}
+
 pub struct Plot {
-    plant: char,
+    region: Region,
     locations: Vec<usize>,
-    perimeter: u32,
-    location_fencing: HashMap<usize, Vec<Direction>>,
 }
 
 impl Garden {
-    pub fn map_plots(&self) -> Vec<Plot> {
-        let mut plots = vec![];
-
-        for plant in self.plants.iter() {
-            let mut all_locations = self.flattened_plants.match_indices(*plant).map(|(i, _)| i).collect::<Vec<_>>();
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
 
-            while !all_locations.is_empty() {
-                let start = all_locations.remove(0);
+    pub fn outlines(&self) -> Vec<Outline> {
+        let RegionLabels { labels, regions } = self.label_regions();
 
-                let plot_locations = map_plot(&self.grid, &self.flattened_plants, *plant, start);
+        trace_outlines(&self.grid, &labels, &regions)
+    }
 
-                all_locations.retain(|l| !plot_locations.contains(l));
+    pub fn map_plots(&self) -> Vec<Plot> {
+        let RegionLabels { labels, regions } = self.label_regions();
 
-                let plot = Plot::new(*plant, plot_locations, self);
+        let mut plots = regions
+            .into_iter()
+            .map(|region| Plot { region, locations: vec![] })
+            .collect::<Vec<_>>();
 
-                plots.push(plot);
-            }
+        for (location, &label) in labels.iter().enumerate() {
+            plots[label].locations.push(location);
         }
 
         plots
     }
 
-    pub fn fencing_price(&self) -> u32 {
-        let plots = self.map_plots();
-
-        plots.iter()
-            .map(|p| p.fencing_price())
-            .sum()
-    }
-
-    pub fn bulk_fencing_price(&self) -> u32 {
-        let plots = self.map_plots();
-
-        plots.iter()
-            .map(|p| p.fencing_bulk_discount_price(&self.grid))
-            .sum()
-    }
-}
-
-impl Plot {
-    pub fn new(plant: char, mut locations: Vec<usize>, garden: &Garden) -> Self {
-        locations.sort();
-
-        let mut perimeter = 0;
-        let mut location_fencing: HashMap<usize, Vec<Direction>> = HashMap::new();
+    /*
+     * one scan joining each cell to the same plant above and to the left, then a second pass to number the
+     * regions in scan order and total up their area, perimeter and bounds
+     */
+    pub fn label_regions(&self) -> RegionLabels {
+        let plants = self.flattened_plants.chars().collect::<Vec<_>>();
 
-        for location in locations.iter() {
-            let position = garden.grid.get_position(*location).unwrap();
+        let column_count = self.grid.column_count as usize;
 
-            for direction in Direction::orthogonal() {
-                let adjacent_position = position.adjacent(*direction);
+        let mut parents = (0..plants.len()).collect::<Vec<_>>();
 
-                let needs_fence = if garden.grid.validate_position(&adjacent_position, false) {
-                    let adjacent = garden.grid.get_index(&adjacent_position).unwrap();
-                    let adjacent_plant = garden.flattened_plants.chars().nth(adjacent).unwrap();
-                    adjacent_plant != plant
-                } else {
-                    true
-                };
-
-                if needs_fence {
-                    perimeter += 1;
+        for index in 0..plants.len() {
+            if index % column_count > 0 && plants[index - 1] == plants[index] {
+                union(&mut parents, index - 1, index);
+            }
 
-                    let fencing = location_fencing.entry(*location).or_default();
-                    fencing.push(*direction);
-                }
+            if index >= column_count && plants[index - column_count] == plants[index] {
+                union(&mut parents, index - column_count, index);
             }
         }
 
-        Self { plant, locations, perimeter, location_fencing }
-    }
-    pub fn contains(&self, location: usize) -> bool {
-        self.locations.contains(&location)
-    }
+        let mut root_labels: Vec<Option<usize>> = vec![None; plants.len()];
+        let mut labels = Vec::with_capacity(plants.len());
+        let mut regions: Vec<Region> = vec![];
+
+        for (index, &plant) in plants.iter().enumerate() {
+            let root = find(&mut parents, index);
+            let position = self.grid.get_position(index).unwrap();
+
+            let label = *root_labels[root].get_or_insert_with(|| {
+                regions.push(Region {
+                    label: regions.len(),
+                    plant,
+                    area: 0,
+                    perimeter: 0,
+                    garden_edge: 0,
+                    sides: 0,
+                    top_left: position.clone(),
+                    bottom_right: position.clone(),
+                });
+
+                regions.len() - 1
+            });
+
+            labels.push(label);
+
+            let region = &mut regions[label];
+
+            region.area += 1;
+            region.perimeter += 4;
+            region.top_left = Position(region.top_left.0.min(position.0), region.top_left.1.min(position.1));
+            region.bottom_right = Position(region.bottom_right.0.max(position.0), region.bottom_right.1.max(position.1));
+
+            // each edge shared with an earlier cell of the region takes a fence off both cells
+            if index % column_count > 0 && labels[index - 1] == label {
+                region.perimeter -= 2;
+            }
 
-    pub fn fencing_price(&self) -> u32 {
-        u32::try_from(self.locations.len()).unwrap() * self.perimeter
-    }
+            if index >= column_count && labels[index - column_count] == label {
+                region.perimeter -= 2;
+            }
 
-    pub fn fencing_bulk_discount_price(&self, grid: &Grid) -> u32 {
-        if self.locations.len() == 1 {
-            return 4;
+            // fences along the outside of the garden are not shared with any other plot
+            let on_edge = [position.0 == 0, position.1 == 0, position.0 == self.grid.row_count - 1, position.1 == self.grid.column_count - 1];
+            region.garden_edge += on_edge.iter().filter(|&&e| e).count() as u32;
         }
 
-        let mut fenced_locations = self.locations.iter()
-            .filter(|l| self.location_fencing.contains_key(l))
-            .map(|l| *l)
-            .collect::<HashSet<_>>();
-
-        let mut fencing_side_count =1;
-
-        let start = *self.locations.first().unwrap();
-
-        let mut current_location = start;
-        let mut current_direction: Option<Direction> = None;
-
-        loop {
-
-            fenced_locations.remove(&current_location);
-
-            let current_position = grid.get_position(current_location).unwrap();
-
-            let fencing = self.location_fencing.get(&current_location);
-
-            let (next_direction, rotations) = {
-                let mut direction = if let Some(current_direction) = current_direction {
-                    current_direction.orthogonal_previous()
-                } else {
-                    Direction::North
-                };
-
-                let mut rotations: i32 = -1;
-
-                for _ in 0..4 {
-                    if fencing.is_none() {
-                        break;
-                    } else if fencing.unwrap().contains(&direction) {
-                        rotations += 1;
-                        direction = direction.orthogonal_next();
-                    } else {
-                        break;
-                    }
-                }
-
-                (direction, rotations)
-            };
-
-            let sides = u32::try_from(rotations.abs()).unwrap();
-
-            fencing_side_count += sides;
-
-            let next_position = current_position.adjacent(next_direction);
-
-            let next_location = grid.get_index(&next_position).unwrap();
-
-            current_location = next_location;
-            current_direction.replace(next_direction);
-            
-            if current_location == start && fenced_locations.is_empty() {
-                let mut current_direction = current_direction.unwrap();
-                
-                loop {
-                    if current_direction == Direction::North {
-                        break;
-                    }
-
-                    current_direction = current_direction.orthogonal_next();
-
-                    fencing_side_count += 1;
+        /*
+         * a region has as many sides as corners, so check the four corners of every cell
+         * convex - both neighbours either side of the corner are outside the region
+         * concave - both neighbours are inside but the diagonal between them is not
+         * comparing labels rather than plants keeps separate regions that touch diagonally apart, and holes
+         * are counted the same way as the outer boundary
+         */
+        let same_region = |position: &Position, label: usize| {
+            self.grid.get_index(position).is_ok_and(|i| labels[i] == label)
+        };
+
+        for (index, &label) in labels.iter().enumerate() {
+            let position = self.grid.get_position(index).unwrap();
+
+            for (before, corner, after) in CORNERS {
+                let before_inside = same_region(&position.adjacent(before), label);
+                let after_inside = same_region(&position.adjacent(after), label);
+                let diagonal_inside = same_region(&position.adjacent(corner), label);
+
+                if (!before_inside && !after_inside) || (before_inside && after_inside && !diagonal_inside) {
+                    regions[label].sides += 1;
                 }
-                break;
             }
         }
 
-        u32::try_from(self.locations.len()).unwrap() * fencing_side_count
+        RegionLabels { labels, regions }
     }
-}
-
-fn map_plot(grid: &Grid, flattened_plants: &str, plant: char, start: usize) -> Vec<usize> {
 
-    let mut final_locations = vec![];
-
-    let mut working_locations = vec![start];
+    pub fn fencing_price(&self) -> u64 {
+        self.bill(&PerimeterPricing).total()
+    }
 
-    while !working_locations.is_empty() {
+    pub fn bulk_fencing_price(&self) -> u64 {
+        self.bill(&SidesPricing).total()
+    }
 
-        let current = working_locations.remove(0);
-        let current_position = grid.get_position(current).unwrap();
+    pub fn bill(&self, pricing: &dyn FencePricing) -> Bill {
+        let lines = self.map_plots()
+            .iter()
+            .map(|plot| BillLine {
+                label: plot.region().label,
+                plant: plot.region().plant,
+                detail: pricing.explain(plot),
+                amount: pricing.price(plot),
+            })
+            .collect();
+
+        Bill { lines }
+    }
+}
 
-        for direction in Direction::orthogonal() {
-            let adjacent_position = current_position.adjacent(*direction);
+const CORNERS: [(Direction, Direction, Direction); 4] = [
+    (Direction::North, Direction::NorthEast, Direction::East),
+    (Direction::East, Direction::SouthEast, Direction::South),
+    (Direction::South, Direction::SouthWest, Direction::West),
+    (Direction::West, Direction::NorthWest, Direction::North),
+];
+
+fn find(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
 
-            if !grid.validate_position(&adjacent_position, false) {
-                continue;
-            }
+    index
+}
 
-            let adjacent = grid.get_index(&adjacent_position).unwrap();
+fn union(parents: &mut [usize], a: usize, b: usize) {
+    let a = find(parents, a);
+    let b = find(parents, b);
 
-            if final_locations.contains(&adjacent) || working_locations.contains(&adjacent) {
-                continue;
-            }
+    // the earliest cell stays the root so regions are numbered in scan order
+    if a < b {
+        parents[b] = a;
+    } else {
+        parents[a] = b;
+    }
+}
 
-            let adjacent_plant = flattened_plants.chars().nth(adjacent).unwrap();
+impl Plot {
+    pub fn region(&self) -> &Region {
+        &self.region
+    }
 
-            if plant == adjacent_plant {
-                working_locations.push(adjacent);
-            }
-        }
+    pub fn contains(&self, location: usize) -> bool {
+        self.locations.contains(&location)
+    }
 
-        final_locations.push(current);
+    pub fn fencing_price(&self) -> u64 {
+        self.region.area as u64 * self.region.perimeter as u64
     }
 
-    final_locations
+    pub fn fencing_bulk_discount_price(&self) -> u64 {
+        self.region.area as u64 * self.region.sides as u64
+    }
 }
 
 impl From<&str> for Garden {
     fn from(input: &str) -> Self {
         let (flattened_plants, grid) = Grid::parse_input(input);
 
-        let mut plants = HashSet::new();
-
-        for p in flattened_plants.chars() {
-            plants.insert(p);
-        }
-
-        let mut plants = plants.into_iter()
-            .collect::<Vec<_>>();
-
-        plants.sort();
-
-        Self { plants, flattened_plants, grid }
+        Self { flattened_plants, grid }
     }
 }
 
@@ -249,7 +246,7 @@ EEEC
 
         assert_eq!(5, plots.len());
 
-        let fencing_price: u32 = plots.iter()
+        let fencing_price: u64 = plots.iter()
             .map(|p| p.fencing_price())
             .sum();
 
@@ -277,7 +274,7 @@ MMMISSJEEE
 
         assert_eq!(11, plots.len());
 
-        let fencing_price: u32 = plots.iter()
+        let fencing_price: u64 = plots.iter()
             .map(|p| p.fencing_price())
             .sum();
 
@@ -317,7 +314,6 @@ EEEEE
         assert_eq!(236, bulk_fencing_price);
     }
 
-    #[ignore = "inner fence"]
     #[test]
     fn find_bulk_fencing_price_abba() {
         let input = r"
@@ -335,4 +331,108 @@ AAAAAA
 
         assert_eq!(368, bulk_fencing_price);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn label_regions() {
+        let input = r"
+OOOOO
+OXOXO
+OOOOO
+OXOXO
+OOOOO
+        ".trim();
+
+        let garden = Garden::from(input);
+
+        let RegionLabels { labels, regions } = garden.label_regions();
+
+        assert_eq!(5, regions.len());
+        assert_eq!(labels[0], 0);
+        assert_eq!(labels[6], 1);
+        assert_eq!(labels[8], 2);
+
+        let outer = &regions[0];
+
+        assert_eq!(('O', 21, 36), (outer.plant, outer.area, outer.perimeter));
+        assert_eq!((Position(0, 0), Position(4, 4)), (outer.top_left.clone(), outer.bottom_right.clone()));
+
+        assert!(regions[1..].iter().all(|r| r.plant == 'X' && r.area == 1 && r.perimeter == 4 && r.top_left == r.bottom_right));
+    }
+
+    #[test]
+    fn label_regions_winding() {
+        // the bottom row joins the two arms after both have been labelled separately
+        let input = r"
+ABA
+ABA
+AAA
+        ".trim();
+
+        let garden = Garden::from(input);
+
+        let RegionLabels { labels, regions } = garden.label_regions();
+
+        assert_eq!(vec![0, 1, 0, 0, 1, 0, 0, 0, 0], labels);
+        assert_eq!(7, regions[0].area);
+        assert_eq!(16, regions[0].perimeter);
+        assert_eq!(Position(2, 2), regions[0].bottom_right);
+    }
+
+    #[test]
+    fn find_bulk_fencing_price_holes() {
+        let input = r"
+OOOOO
+OXOXO
+OOOOO
+OXOXO
+OOOOO
+        ".trim();
+
+        let garden = Garden::from(input);
+
+        let RegionLabels { regions, .. } = garden.label_regions();
+
+        // four outer sides plus four around each hole
+        assert_eq!(20, regions[0].sides);
+        assert_eq!(436, garden.bulk_fencing_price());
+    }
+
+    #[test]
+    fn find_bulk_fencing_price_diagonal() {
+        let input = r"
+AAAAAA
+AAABBA
+AAABBA
+ABBAAA
+ABBAAA
+AAAAAA
+        ".trim();
+
+        let garden = Garden::from(input);
+
+        let RegionLabels { regions, .. } = garden.label_regions();
+
+        // the two B squares touch only at a corner, which pinches the A region into a twelve sided shape
+        assert_eq!(vec![12, 4, 4], regions.iter().map(|r| r.sides).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn find_bulk_fencing_price_simple() {
+        let input = r"
+RRRRIICCFF
+RRRRIICCCF
+VVRRRCCFFF
+VVRCCCJFFF
+VVVVCJJCFE
+VVIVCCJJEE
+VVIIICJJEE
+MIIIIIJJEE
+MIIISIJEEE
+MMMISSJEEE
+        ".trim();
+
+        let garden = Garden::from(input);
+
+        assert_eq!(1206, garden.bulk_fencing_price());
+    }
+}
@@ -1,7 +1,11 @@
 use garden::Garden;
+use outline::{to_geojson, to_svg};
+use pricing::{pricing_from, FencePricing};
 use utils::{part_selection, Part, Timer};
 
 mod garden;
+mod outline;
+mod pricing;
 
 fn main() {
     let _timer = Timer::new();
@@ -12,14 +16,88 @@ fn main() {
 
     match part {
         Part::One => part_1(&text),
-        Part::Two => unimplemented!(),
+        Part::Two => part_2(&text),
     }
 }
 
 fn part_1(input: &str)     {
     let garden = Garden::from(input);
 
-    let fencing_price = garden.fencing_price();
+    if std::env::args().any(|a| a == "--regions") {
+        for plot in garden.map_plots() {
+            let region = plot.region();
+            println!(
+                "{} {}: area {}, perimeter {}, bounds ({}, {}) to ({}, {})",
+                region.plant,
+                region.label,
+                region.area,
+                region.perimeter,
+                region.top_left.0,
+                region.top_left.1,
+                region.bottom_right.0,
+                region.bottom_right.1,
+            );
+        }
+    }
+
+    match pricing_selection() {
+        Some(pricing) => print_bill(&garden, pricing.as_ref()),
+        None => {
+            let fencing_price = garden.fencing_price();
+
+            println!("{fencing_price}");
+        },
+    }
+}
+
+fn part_2(input: &str) {
+    let garden = Garden::from(input);
+
+    let args = std::env::args().collect::<Vec<_>>();
+
+    // exports are written on their own so the output can be redirected straight to a file
+    if args.iter().any(|a| a == "--svg") {
+        println!("{}", to_svg(garden.grid(), &garden.outlines()));
+        return;
+    } else if args.iter().any(|a| a == "--geojson") {
+        println!("{}", to_geojson(&garden.outlines()));
+        return;
+    }
+
+    match pricing_selection() {
+        Some(pricing) => print_bill(&garden, pricing.as_ref()),
+        None => {
+            let bulk_fencing_price = garden.bulk_fencing_price();
 
-    println!("{fencing_price}");
+            println!("{bulk_fencing_price}");
+        },
+    }
+}
+
+fn print_bill(garden: &Garden, pricing: &dyn FencePricing) {
+    let bill = garden.bill(pricing);
+
+    if std::env::args().any(|a| a == "--bill") {
+        for line in bill.lines.iter() {
+            println!("{} {}: {} = {}", line.plant, line.label, line.detail, line.amount);
+        }
+    }
+
+    println!("{}", bill.total());
+}
+
+// --pricing <model> picks a pricing model, --bill on its own itemises the part's usual pricing
+fn pricing_selection() -> Option<Box<dyn FencePricing>> {
+    let args = std::env::args().collect::<Vec<_>>();
+
+    let default = match args.get(1).map(|a| a.as_str()) {
+        Some("--part-2") => "sides",
+        _ => "perimeter",
+    };
+
+    match args.iter().position(|a| a == "--pricing") {
+        Some(index) => Some(pricing_from(args.get(index + 1).expect("Missing pricing model"))),
+        None if args.iter().any(|a| a == "--bill") => Some(pricing_from(default)),
+        None => None,
+    }
 }
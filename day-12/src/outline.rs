@@ -0,0 +1,365 @@
+use std::collections::BTreeMap;
+
+use utils::{Direction, Grid, Position};
+
+use crate::garden::Region;
+
+// a straight run of fence between two lattice points, facing away from the region
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Side {
+    pub facing: Direction,
+    pub start: Position,
+    pub end: Position,
+}
+
+// closed loop of sides walked with the region on the right
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ring {
+    pub sides: Vec<Side>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Outline {
+    pub region: Region,
+    pub outer: Ring,
+    pub holes: Vec<Ring>,
+}
+
+impl Ring {
+    // twice the enclosed area, positive for an outer boundary and negative for a hole
+    fn double_area(&self) -> i64 {
+        self.sides.iter()
+            .map(|s| s.start.1 as i64 * s.end.0 as i64 - s.end.1 as i64 * s.start.0 as i64)
+            .sum()
+    }
+
+    pub fn points(&self) -> Vec<Position> {
+        self.sides.iter().map(|s| s.start.clone()).collect()
+    }
+}
+
+impl Outline {
+    pub fn sides(&self) -> usize {
+        self.outer.sides.len() + self.holes.iter().map(|h| h.sides.len()).sum::<usize>()
+    }
+
+    fn rings(&self) -> impl Iterator<Item = &Ring> {
+        std::iter::once(&self.outer).chain(self.holes.iter())
+    }
+}
+
+// the direction the fence is walked in when it faces the given way, keeping the region on the right
+fn travel(facing: Direction) -> Direction {
+    facing.orthogonal_next()
+}
+
+/*
+ * every cell edge bordering another region is a fence, these are chained corner to corner into rings
+ * where a region touches itself diagonally two fences leave the same corner - turning left keeps following
+ * the same neighbouring plants, so each ring goes round one connected patch outside the region and holes
+ * touching at a corner stay separate
+ */
+pub fn trace_outlines(grid: &Grid, labels: &[usize], regions: &[Region]) -> Vec<Outline> {
+    let mut region_fences: Vec<BTreeMap<(i32, i32), Vec<Direction>>> = vec![BTreeMap::new(); regions.len()];
+
+    for (index, &label) in labels.iter().enumerate() {
+        let position = grid.get_position(index).unwrap();
+
+        for &facing in Direction::orthogonal() {
+            let fenced = grid.get_index(&position.adjacent(facing)).map_or(true, |i| labels[i] != label);
+
+            if fenced {
+                let start = match facing {
+                    Direction::North => (position.0, position.1),
+                    Direction::East => (position.0, position.1 + 1),
+                    Direction::South => (position.0 + 1, position.1 + 1),
+                    _ => (position.0 + 1, position.1),
+                };
+
+                region_fences[label].entry(start).or_default().push(facing);
+            }
+        }
+    }
+
+    regions.iter()
+        .zip(region_fences)
+        .map(|(region, mut fences)| {
+            let mut rings = vec![];
+
+            while let Some((&start, _)) = fences.iter().next() {
+                rings.push(trace_ring(&mut fences, start));
+            }
+
+            let (outer, holes): (Vec<_>, Vec<_>) = rings.into_iter().partition(|r| r.double_area() > 0);
+
+            assert_eq!(outer.len(), 1, "Region {} should have exactly one outer boundary", region.label);
+
+            Outline { region: region.clone(), outer: outer.into_iter().next().unwrap(), holes }
+        })
+        .collect()
+}
+
+fn trace_ring(fences: &mut BTreeMap<(i32, i32), Vec<Direction>>, start: (i32, i32)) -> Ring {
+    let mut sides: Vec<Side> = vec![];
+
+    let mut corner = start;
+    let mut previous: Option<Direction> = None;
+
+    loop {
+        let leaving = fences.get_mut(&corner).unwrap();
+
+        let choice = match previous {
+            Some(facing) => [facing.orthogonal_previous(), facing, facing.orthogonal_next()]
+                .into_iter()
+                .find_map(|f| leaving.iter().position(|&l| l == f))
+                .unwrap(),
+            None => 0,
+        };
+
+        let facing = leaving.remove(choice);
+
+        if leaving.is_empty() {
+            fences.remove(&corner);
+        }
+
+        let next = Position(corner.0, corner.1).adjacent(travel(facing));
+        let next = (next.0, next.1);
+
+        match sides.last_mut() {
+            Some(side) if side.facing == facing => side.end = Position(next.0, next.1),
+            _ => sides.push(Side { facing, start: Position(corner.0, corner.1), end: Position(next.0, next.1) }),
+        }
+
+        corner = next;
+        previous = Some(facing);
+
+        if corner == start {
+            break;
+        }
+    }
+
+    Ring { sides }
+}
+
+fn plant_colour(plant: char) -> String {
+    let hue = (plant as u32).wrapping_mul(47) % 360;
+
+    format!("hsl({hue},60%,70%)")
+}
+
+pub fn to_svg(grid: &Grid, outlines: &[Outline]) -> String {
+    let scale = 10;
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}">"#,
+        grid.column_count * scale,
+        grid.row_count * scale,
+    );
+    svg.push('\n');
+
+    for outline in outlines.iter() {
+        let path = outline.rings()
+            .map(|ring| {
+                let points = ring.points()
+                    .iter()
+                    .map(|p| format!("{} {}", p.1 * scale, p.0 * scale))
+                    .collect::<Vec<_>>();
+
+                format!("M {} Z", points.join(" L "))
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        svg.push_str(&format!(
+            r#"<path d="{path}" fill="{}" fill-rule="evenodd" data-plant="{}" data-sides="{}"/>"#,
+            plant_colour(outline.region.plant),
+            xml_attribute(&outline.region.plant.to_string()),
+            outline.sides(),
+        ));
+        svg.push('\n');
+
+        for side in outline.rings().flat_map(|r| r.sides.iter()) {
+            svg.push_str(&format!(
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="black" data-facing="{}"/>"#,
+                side.start.1 * scale,
+                side.start.0 * scale,
+                side.end.1 * scale,
+                side.end.0 * scale,
+                side.facing,
+            ));
+            svg.push('\n');
+        }
+    }
+
+    svg.push_str("</svg>");
+
+    svg
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::from('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str(r#"\""#),
+            '\\' => json.push_str(r"\\"),
+            c if c.is_control() => json.push_str(&format!(r"\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+
+    json
+}
+
+fn xml_attribute(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '"' => "&quot;".to_string(),
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+fn json_point(position: &Position) -> String {
+    format!("[{},{}]", position.1, position.0)
+}
+
+// GeoJSON style feature collection with x as the column and y as the row
+pub fn to_geojson(outlines: &[Outline]) -> String {
+    let features = outlines.iter()
+        .map(|outline| {
+            let rings = outline.rings()
+                .map(|ring| {
+                    let mut points = ring.points();
+                    points.push(points[0].clone());
+
+                    format!("[{}]", points.iter().map(json_point).collect::<Vec<_>>().join(","))
+                })
+                .collect::<Vec<_>>();
+
+            let sides = outline.rings()
+                .flat_map(|r| r.sides.iter())
+                .map(|s| format!(r#"{{"facing":"{}","start":{},"end":{}}}"#, s.facing, json_point(&s.start), json_point(&s.end)))
+                .collect::<Vec<_>>();
+
+            format!(
+                r#"{{"type":"Feature","properties":{{"label":{},"plant":{},"area":{},"perimeter":{},"sides":[{}]}},"geometry":{{"type":"Polygon","coordinates":[{}]}}}}"#,
+                outline.region.label,
+                json_string(&outline.region.plant.to_string()),
+                outline.region.area,
+                outline.region.perimeter,
+                sides.join(","),
+                rings.join(","),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    format!(r#"{{"type":"FeatureCollection","features":[{}]}}"#, features.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::garden::Garden;
+
+    use super::*;
+
+    #[test]
+    fn outline_with_holes() {
+        let garden = Garden::from(r"
+OOOOO
+OXOXO
+OOOOO
+OXOXO
+OOOOO
+        ");
+
+        let outlines = garden.outlines();
+
+        let outer = &outlines[0];
+
+        assert_eq!(4, outer.holes.len());
+        assert_eq!(outer.outer.points(), vec![Position(0, 0), Position(0, 5), Position(5, 5), Position(5, 0)]);
+        assert_eq!(outer.holes[0].points(), vec![Position(1, 1), Position(2, 1), Position(2, 2), Position(1, 2)]);
+        assert_eq!(outer.holes[0].sides[0].facing, Direction::East);
+
+        assert!(outlines.iter().all(|o| o.sides() as u32 == o.region.sides));
+    }
+
+    #[test]
+    fn outline_pinched() {
+        let garden = Garden::from(r"
+AAAAAA
+AAABBA
+AAABBA
+ABBAAA
+ABBAAA
+AAAAAA
+        ");
+
+        let outlines = garden.outlines();
+
+        // the B squares touching at a corner leave two separate holes
+        assert_eq!(2, outlines[0].holes.len());
+        assert!(outlines.iter().all(|o| o.sides() as u32 == o.region.sides));
+
+        let garden = Garden::from(r"
+EEEEE
+EXXXX
+EEEEE
+EXXXX
+EEEEE
+        ");
+
+        let outlines = garden.outlines();
+
+        assert_eq!(12, outlines[0].sides());
+        assert!(outlines[0].holes.is_empty());
+    }
+
+    #[test]
+    fn export() {
+        let garden = Garden::from(r"
+AB
+BB
+        ");
+
+        let outlines = garden.outlines();
+
+        let json = to_geojson(&outlines[..1]);
+
+        assert_eq!(
+            json,
+            r#"{"type":"FeatureCollection","features":[{"type":"Feature","properties":{"label":0,"plant":"A","area":1,"perimeter":4,"sides":[{"facing":"North","start":[0,0],"end":[1,0]},{"facing":"East","start":[1,0],"end":[1,1]},{"facing":"South","start":[1,1],"end":[0,1]},{"facing":"West","start":[0,1],"end":[0,0]}]},"geometry":{"type":"Polygon","coordinates":[[[0,0],[1,0],[1,1],[0,1],[0,0]]]}}]}"#
+        );
+
+        let svg = to_svg(garden.grid(), &outlines);
+
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 20 20">"#));
+        assert!(svg.contains(r#"<path d="M 0 0 L 10 0 L 10 10 L 0 10 Z" "#));
+        assert_eq!(10, svg.matches("<line").count());
+    }
+
+    #[test]
+    fn export_escapes_plants() {
+        let garden = Garden::from(r#"
+"\
+"\
+        "#);
+
+        let outlines = garden.outlines();
+
+        let json = to_geojson(&outlines);
+
+        assert!(json.contains(r#""plant":"\"""#));
+        assert!(json.contains(r#""plant":"\\""#));
+
+        let svg = to_svg(garden.grid(), &outlines);
+
+        assert!(svg.contains(r#"data-plant="&quot;""#));
+    }
+}
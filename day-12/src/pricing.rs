@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use crate::garden::Plot;
+
+pub trait FencePricing {
+    fn price(&self, plot: &Plot) -> u64;
+
+    fn explain(&self, plot: &Plot) -> String {
+        format!("area {}", plot.region().area)
+    }
+}
+
+// area × perimeter
+pub struct PerimeterPricing;
+
+// area × sides, the bulk discount
+pub struct SidesPricing;
+
+// another model's price scaled by a rate for each plant
+pub struct PlantRates {
+    pub base: Box<dyn FencePricing>,
+    pub rates: HashMap<char, u64>,
+    pub default_rate: u64,
+}
+
+// another model's price plus a fixed charge for every corner, and so every side
+pub struct CornerSurcharge {
+    pub base: Box<dyn FencePricing>,
+    pub per_corner: u64,
+}
+
+// area × perimeter, with fences shared with a neighbouring plot discounted by a percentage
+pub struct SharedFenceDiscount {
+    pub percent: u64,
+}
+
+impl FencePricing for PerimeterPricing {
+    fn price(&self, plot: &Plot) -> u64 {
+        plot.fencing_price()
+    }
+
+    fn explain(&self, plot: &Plot) -> String {
+        format!("area {} × perimeter {}", plot.region().area, plot.region().perimeter)
+    }
+}
+
+impl FencePricing for SidesPricing {
+    fn price(&self, plot: &Plot) -> u64 {
+        plot.fencing_bulk_discount_price()
+    }
+
+    fn explain(&self, plot: &Plot) -> String {
+        format!("area {} × sides {}", plot.region().area, plot.region().sides)
+    }
+}
+
+impl FencePricing for PlantRates {
+    fn price(&self, plot: &Plot) -> u64 {
+        self.base.price(plot).checked_mul(self.rate(plot)).expect("Fence price overflowed u64")
+    }
+
+    fn explain(&self, plot: &Plot) -> String {
+        format!("({}) × rate {}", self.base.explain(plot), self.rate(plot))
+    }
+}
+
+impl PlantRates {
+    fn rate(&self, plot: &Plot) -> u64 {
+        *self.rates.get(&plot.region().plant).unwrap_or(&self.default_rate)
+    }
+}
+
+impl FencePricing for CornerSurcharge {
+    fn price(&self, plot: &Plot) -> u64 {
+        self.per_corner
+            .checked_mul(plot.region().sides as u64)
+            .and_then(|surcharge| self.base.price(plot).checked_add(surcharge))
+            .expect("Fence price overflowed u64")
+    }
+
+    fn explain(&self, plot: &Plot) -> String {
+        format!("{} + corners {} × {}", self.base.explain(plot), plot.region().sides, self.per_corner)
+    }
+}
+
+impl FencePricing for SharedFenceDiscount {
+    fn price(&self, plot: &Plot) -> u64 {
+        let region = plot.region();
+
+        let edge = region.garden_edge as u64;
+        let shared = (region.perimeter - region.garden_edge) as u64;
+
+        // perimeter fits in u32, so the weighted fence length fits in u64
+        (edge * 100 + shared * (100 - self.percent))
+            .checked_mul(region.area as u64)
+            .expect("Fence price overflowed u64")
+            / 100
+    }
+
+    fn explain(&self, plot: &Plot) -> String {
+        let region = plot.region();
+
+        format!(
+            "area {} × (edge {} + shared {} at {}% off)",
+            region.area,
+            region.garden_edge,
+            region.perimeter - region.garden_edge,
+            self.percent,
+        )
+    }
+}
+
+/*
+ * perimeter, sides, corners=<surcharge>, shared=<percent off>, or rates=<plant>:<rate>;... on the perimeter price
+ * e.g. "rates=A:2;B:3"
+ */
+pub fn pricing_from(spec: &str) -> Box<dyn FencePricing> {
+    let (name, value) = spec.split_once('=').unwrap_or((spec, ""));
+
+    match name {
+        "perimeter" => Box::new(PerimeterPricing),
+        "sides" => Box::new(SidesPricing),
+        "corners" => Box::new(CornerSurcharge { base: Box::new(SidesPricing), per_corner: value.parse().unwrap() }),
+        "shared" => {
+            let percent = value.parse().unwrap();
+            assert!(percent <= 100, "Shared fence discount must be at most 100%");
+            Box::new(SharedFenceDiscount { percent })
+        },
+        "rates" => {
+            let rates = value.split(';')
+                .map(|rate| {
+                    let (plant, rate) = rate.split_once(':').expect("Plant rates expect <plant>:<rate>");
+                    (plant.chars().next().unwrap(), rate.parse().unwrap())
+                })
+                .collect();
+
+            Box::new(PlantRates { base: Box::new(PerimeterPricing), rates, default_rate: 1 })
+        },
+        _ => panic!("Unknown pricing '{spec}' - expected perimeter, sides, corners=, shared=, or rates="),
+    }
+}
+
+pub struct BillLine {
+    pub label: usize,
+    pub plant: char,
+    pub detail: String,
+    pub amount: u64,
+}
+
+pub struct Bill {
+    pub lines: Vec<BillLine>,
+}
+
+impl Bill {
+    pub fn total(&self) -> u64 {
+        self.lines
+            .iter()
+            .try_fold(0u64, |total, line| total.checked_add(line.amount))
+            .expect("Bill total overflowed u64")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::garden::Garden;
+
+    use super::*;
+
+    const BASIC_EXAMPLE: &str = r"
+AAAA
+BBCD
+BBCC
+EEEC
+    ";
+
+    #[test]
+    fn built_in_pricing() {
+        let garden = Garden::from(BASIC_EXAMPLE);
+
+        let bill = garden.bill(&PerimeterPricing);
+
+        assert_eq!(140, bill.total());
+        assert_eq!(5, bill.lines.len());
+        assert_eq!(('A', "area 4 × perimeter 10", 40), (bill.lines[0].plant, bill.lines[0].detail.as_str(), bill.lines[0].amount));
+
+        assert_eq!(80, garden.bill(&SidesPricing).total());
+    }
+
+    #[test]
+    fn custom_pricing() {
+        let garden = Garden::from(BASIC_EXAMPLE);
+
+        // A costs double, everything else as normal
+        let bill = garden.bill(pricing_from("rates=A:2").as_ref());
+        assert_eq!(180, bill.total());
+
+        // 24 sides in total
+        let bill = garden.bill(pricing_from("corners=5").as_ref());
+        assert_eq!(80 + 24 * 5, bill.total());
+        assert_eq!("area 4 × sides 4 + corners 4 × 5", bill.lines[0].detail);
+
+        // the D plot has one fence on the edge of the garden and three shared with its neighbours
+        let bill = garden.bill(pricing_from("shared=50").as_ref());
+        let d = bill.lines.iter().find(|l| l.plant == 'D').unwrap();
+        assert_eq!(("area 1 × (edge 1 + shared 3 at 50% off)", 2), (d.detail.as_str(), d.amount));
+
+        assert_eq!(garden.bill(pricing_from("shared=0").as_ref()).total(), 140);
+    }
+
+    #[test]
+    fn large_prices() {
+        let garden = Garden::from(BASIC_EXAMPLE);
+
+        // well past u32, A's 40 is scaled to 40 × 2^32
+        let bill = garden.bill(pricing_from("rates=A:4294967296").as_ref());
+        assert_eq!(40 * 4294967296 + 100, bill.total());
+    }
+
+    #[test]
+    #[should_panic(expected = "Fence price overflowed u64")]
+    fn price_overflow() {
+        Garden::from(BASIC_EXAMPLE).bill(pricing_from("rates=A:18446744073709551615").as_ref());
+    }
+}
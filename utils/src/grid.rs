@@ -89,6 +89,19 @@ impl Direction {
         }
     }
 
+    pub fn flip(&self) -> Direction {
+        match *self {
+            Direction::North => Direction::South,
+            Direction::NorthEast => Direction::SouthWest,
+            Direction::East => Direction::West,
+            Direction::SouthEast => Direction::NorthWest,
+            Direction::South => Direction::North,
+            Direction::SouthWest => Direction::NorthEast,
+            Direction::West => Direction::East,
+            Direction::NorthWest => Direction::SouthEast,
+        }
+    }
+
     pub fn orthogonal_rotate(&self, clicks: u8) -> Direction {
         let mut direction = *self;
 
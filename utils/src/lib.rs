@@ -1,9 +1,12 @@
 mod timer;
 mod part;
 mod grid;
+mod random;
 
 pub use timer::Timer;
 
 pub use part::{Part, part_selection};
 
-pub use grid::{Position, Direction, Grid, Velocity};
\ No newline at end of file
+pub use grid::{Position, Direction, Grid, Velocity};
+
+pub use random::Random;
\ No newline at end of file